@@ -0,0 +1,214 @@
+// `asm6502!` runs the crate's assembler at compile time, so programs in tests can be
+// written as source instead of bytes:
+//
+//     cpu.load_and_run(asm6502! { LDA #$05; TAX; BRK }).unwrap();
+//
+// It expands to a `Vec<u8>` holding the program image. `;` separates statements where
+// source would have new lines, and Rust comments can be used instead of assembler
+// ones. The encodings are those of the NMOS 6502.
+//
+// Rust's tokenizer reads hex numbers like `$1E` as a malformed exponent and rejects
+// them before the macro sees them. Source needing those can be given as a string
+// instead, with lines and comments as in a source file: `asm6502!("LDA #$1E")`.
+//
+// Assembler errors become compile errors. They point at the token the assembler
+// reported, usually the mnemonic, or at the string for string source.
+
+use cpu_6502::asm::Assembler;
+use cpu_6502::cpu::variant::Variant;
+use proc_macro::{Delimiter, Group, Literal, Span, TokenStream, TokenTree};
+
+/// Assembles 6502 source into a `Vec<u8>` at compile time.
+///
+/// ```
+/// use cpu_6502_macros::asm6502;
+///
+/// assert_eq!(asm6502! { LDA #$05; TAX; BRK }, vec![0xa9, 0x05, 0xaa, 0x00]);
+/// assert_eq!(asm6502!("LDA #$1E"), vec![0xa9, 0x1e]);
+/// ```
+#[proc_macro]
+pub fn asm6502(input: TokenStream) -> TokenStream {
+    let assembler = Assembler::new(Variant::Nmos6502);
+    let mut tokens = input.clone().into_iter();
+
+    if let (Some(TokenTree::Literal(literal)), None) = (tokens.next(), tokens.next()) {
+        if let Some(source) = string_value(&literal) {
+            return match source {
+                Ok(source) => match assembler.assemble(&source) {
+                    Ok(program) => byte_vec(&program.bytes),
+                    Err(error) => compile_error(&error.to_string(), literal.span()),
+                },
+                Err(message) => compile_error(&message, literal.span()),
+            };
+        }
+    }
+
+    let source = Source::new(input);
+    match assembler.assemble(&source.text) {
+        Ok(program) => byte_vec(&program.bytes),
+        Err(error) => compile_error(
+            &error.kind.to_string(),
+            source.span_at(error.line, error.column),
+        ),
+    }
+}
+
+// Source text rebuilt from tokens, remembering where each token went
+struct Source {
+    text: String,
+    // Line and column of each token in `text`, with its span
+    positions: Vec<(usize, usize, Span)>,
+    line: usize,
+    column: usize,
+    // The span of the token before, if it is on the same line
+    previous: Option<Span>,
+}
+
+impl Source {
+    fn new(input: TokenStream) -> Self {
+        let mut source = Source {
+            text: String::new(),
+            positions: vec![],
+            line: 1,
+            column: 1,
+            previous: None,
+        };
+        source.add_stream(input);
+        source
+    }
+
+    fn add_stream(&mut self, stream: TokenStream) {
+        for tree in stream {
+            match tree {
+                TokenTree::Group(group) => self.add_group(group),
+                TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                    self.text.push('\n');
+                    self.line += 1;
+                    self.column = 1;
+                    self.previous = None;
+                }
+                TokenTree::Punct(punct) => self.add(&punct.to_string(), punct.span()),
+                TokenTree::Ident(ident) => self.add(&ident.to_string(), ident.span()),
+                TokenTree::Literal(literal) => self.add(&literal.to_string(), literal.span()),
+            }
+        }
+    }
+
+    fn add_group(&mut self, group: Group) {
+        let (open, close) = match group.delimiter() {
+            Delimiter::Parenthesis => ("(", ")"),
+            Delimiter::Bracket => ("[", "]"),
+            Delimiter::Brace => ("{", "}"),
+            // Groups from `macro_rules!` fragments, which have no text of their own
+            Delimiter::None => ("", ""),
+        };
+        if !open.is_empty() {
+            self.add(open, group.span_open());
+        }
+        self.add_stream(group.stream());
+        if !close.is_empty() {
+            self.add(close, group.span_close());
+        }
+    }
+
+    // Appends a token, keeping it apart from the one before unless they touch in the
+    // Rust source, as in `$05` or `.byte`
+    fn add(&mut self, text: &str, span: Span) {
+        if let Some(previous) = self.previous {
+            let (end, start) = (previous.end(), span.start());
+            if (end.line(), end.column()) != (start.line(), start.column()) {
+                self.text.push(' ');
+                self.column += 1;
+            }
+        }
+        self.positions.push((self.line, self.column, span));
+        self.text.push_str(text);
+        self.column += text.chars().count();
+        self.previous = Some(span);
+    }
+
+    // The span of the token at or before a position in `text`
+    fn span_at(&self, line: usize, column: usize) -> Span {
+        self.positions
+            .iter()
+            .filter(|(token_line, _, _)| *token_line == line)
+            .take_while(|(_, token_column, _)| *token_column <= column)
+            .last()
+            .or_else(|| {
+                self.positions
+                    .iter()
+                    .find(|(token_line, _, _)| *token_line == line)
+            })
+            .map_or_else(Span::call_site, |(_, _, span)| *span)
+    }
+}
+
+// The value of a string literal, `None` if the literal is not a string, or an error
+// for escapes the assembler would not need
+fn string_value(literal: &Literal) -> Option<Result<String, String>> {
+    let text = literal.to_string();
+
+    if let Some(raw) = text.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = &raw[hashes..];
+        return Some(Ok(body[1..body.len() - 1 - hashes].to_string()));
+    }
+
+    let body = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('0') => value.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+            // A line continuation skips the line break and leading whitespace
+            Some('\n') => {
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            other => {
+                return Some(Err(format!(
+                    "unsupported escape '\\{}' in assembler source",
+                    other.map_or(String::new(), String::from)
+                )))
+            }
+        }
+    }
+    Some(Ok(value))
+}
+
+fn byte_vec(bytes: &[u8]) -> TokenStream {
+    let items: Vec<String> = bytes.iter().map(|byte| format!("{:#04x}", byte)).collect();
+    format!("::std::vec::Vec::<u8>::from([{}])", items.join(", "))
+        .parse()
+        .unwrap()
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    let tokens: TokenStream = format!("::core::compile_error!({:?})", message)
+        .parse()
+        .unwrap();
+    respan(tokens, span)
+}
+
+fn respan(stream: TokenStream, span: Span) -> TokenStream {
+    stream
+        .into_iter()
+        .map(|mut tree| {
+            if let TokenTree::Group(group) = &tree {
+                let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                tree = TokenTree::Group(respanned);
+            }
+            tree.set_span(span);
+            tree
+        })
+        .collect()
+}
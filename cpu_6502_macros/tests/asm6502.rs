@@ -0,0 +1,82 @@
+use cpu_6502::cpu::bus::Bus;
+use cpu_6502::cpu::variant::Variant;
+use cpu_6502::cpu::CPU;
+use cpu_6502_macros::asm6502;
+
+#[test]
+fn test_assembles_statements() {
+    assert_eq!(
+        asm6502! { LDA #$05; TAX; BRK },
+        vec![0xa9, 0x05, 0xaa, 0x00]
+    );
+    assert_eq!(asm6502! {}, Vec::<u8>::new());
+}
+
+#[test]
+fn test_operands() {
+    assert_eq!(
+        asm6502! {
+            LDA $10,X;
+            STA $0200,Y;
+            LDA ($20),Y;
+            STA ($30,X);
+            JMP ($FFFC);
+            LDA #%0101;
+            LDX #'a';
+            ASL A
+        },
+        vec![
+            0xb5, 0x10, 0x99, 0x00, 0x02, 0xb1, 0x20, 0x81, 0x30, 0x6c, 0xfc, 0xff, 0xa9, 0x05,
+            0xa2, 0x61, 0x0a,
+        ]
+    );
+}
+
+#[test]
+fn test_labels_and_directives() {
+    assert_eq!(
+        asm6502! {
+            count = 3;
+            start: LDX #count;
+            @loop: DEX;       // Local to start
+                   BNE @loop;
+                   LDA table + 1;
+                   BRK;
+            table: .byte 1, 2, "ab";
+                   .word start
+        },
+        vec![
+            0xa2, 0x03, 0xca, 0xd0, 0xfd, 0xad, 0x0a, 0x80, 0x00, 0x01, 0x02, 0x61, 0x62, 0x00,
+            0x80,
+        ]
+    );
+}
+
+#[test]
+fn test_string_source() {
+    let program = asm6502!(
+        "
+        LDA #$1E    ; Not valid as Rust tokens
+        STA $0E
+        BRK
+    "
+    );
+    assert_eq!(program, vec![0xa9, 0x1e, 0x85, 0x0e, 0x00]);
+    assert_eq!(asm6502!(r"LDY #$3E"), vec![0xa0, 0x3e]);
+}
+
+#[test]
+fn test_runs_on_cpu() {
+    let mut cpu = CPU::new(Variant::Nmos6502);
+    cpu.load_and_run(asm6502! {
+        LDA #$05;
+        TAX;
+        INX;
+        STX $10;
+        BRK
+    })
+    .unwrap();
+    assert_eq!(cpu.register_a, 0x05);
+    assert_eq!(cpu.register_x, 0x06);
+    assert_eq!(cpu.bus.peek(0x10), Some(0x06));
+}
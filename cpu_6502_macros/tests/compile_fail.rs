@@ -0,0 +1,6 @@
+// The expected errors in tests/ui check that each one points at the offending token
+#[test]
+fn test_compile_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,5 @@
+use cpu_6502_macros::asm6502;
+
+fn main() {
+    let _ = asm6502! { LDX #0; BNE nowhere };
+}
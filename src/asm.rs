@@ -0,0 +1,1572 @@
+// A two-pass 6502 assembler. Its encodings come from the same decode tables the CPU
+// uses, so it knows every instruction of the selected variant.
+//
+//     start:  LDX #0
+//     @loop:  LDA table,X     ; @loop is local to start
+//             STA $0200,X
+//             INX
+//             CPX #table_end - table
+//             BNE @loop
+//             BRK
+//     table:  .byte 1, 2, "abc"
+//     table_end:
+//
+// Operands below $100 use zero page addressing where the instruction has it, unless
+// the value is a forward reference or a hex literal written with more than two digits,
+// like `$0010`. The first pass sizes every instruction and the second encodes them
+// with the sizes chosen in the first.
+//
+// Larger projects can use:
+//
+// - `.macro name a, b` ... `.endmacro`, invoked as `name 1, 2`. Arguments replace the
+//   parameter names token by token. Local labels in the body are local to each
+//   expansion, while those in arguments keep the caller's scope.
+// - `.if expr` ... `.else` ... `.endif`. The condition must only use symbols defined
+//   above it.
+// - `.include "file"` and `.incbin "file"`, relative to the including file, or to the
+//   working directory for source that does not come from a file.
+// - `.res count[, value]` (or `.fill`) to emit a run of equal bytes.
+// - `.segment "name"` to switch between independent streams of code, each with its
+//   own `.org`. A new segment starts where the previous one ended. The program image
+//   spans all segments, which must not overlap.
+
+pub mod error;
+mod lexer;
+mod parser;
+
+use crate::cpu::instruction_set::instruction::addressing_mode::AddressingMode;
+use crate::cpu::variant::Variant;
+use error::{AsmError, AsmErrorKind, MacroCall};
+use lexer::{Spanned, Token};
+use parser::{BinaryOp, DataItem, Expr, Label, Operand, Statement};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Where code goes without an `.org`. It matches where `CPU::load` puts programs.
+const DEFAULT_ORIGIN: u16 = 0x8000;
+const DEFAULT_SEGMENT: &str = "CODE";
+
+// Limits nested includes and macro expansions, which could otherwise recurse forever
+const MAX_NESTING: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    // Address of the first byte. Gaps between `.org`s and segments are filled with
+    // zeros.
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+    // Labels and constants. Local labels appear as `global@local`.
+    pub symbols: BTreeMap<String, u16>,
+    // The segments that hold code or data, in the order they were first used
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+pub struct Assembler {
+    opcodes: HashMap<(&'static str, AddressingMode), u8>,
+    mnemonics: HashSet<&'static str>,
+}
+
+impl Assembler {
+    pub fn new(variant: Variant) -> Self {
+        let mut opcodes = HashMap::new();
+        let mut mnemonics = HashSet::new();
+
+        // Where several opcodes share a mnemonic and mode, like the undocumented NOPs,
+        // prefer the documented one, then the lowest
+        let mut instructions: Vec<_> = variant.instruction_map().values().collect();
+        instructions.sort_by_key(|instruction| (!instruction.documented, instruction.opcode));
+        for instruction in instructions {
+            opcodes
+                .entry((instruction.mnemonic, instruction.mode))
+                .or_insert(instruction.opcode);
+            mnemonics.insert(instruction.mnemonic);
+        }
+
+        Assembler { opcodes, mnemonics }
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Program, AsmError> {
+        self.assemble_lines(&tokenize_source(source, None)?, None)
+    }
+
+    pub fn assemble_file<P: AsRef<Path>>(&self, path: P) -> Result<Program, AsmError> {
+        let path = path.as_ref().to_path_buf();
+        let source = fs::read_to_string(&path).map_err(|error| io_error(&path, error, None))?;
+        self.assemble_lines(&tokenize_source(&source, Some(&path))?, Some(path))
+    }
+
+    fn assemble_lines(
+        &self,
+        lines: &[SourceLine],
+        file: Option<PathBuf>,
+    ) -> Result<Program, AsmError> {
+        let mut symbols = HashMap::new();
+        let mut modes = vec![];
+
+        Pass::new(self, &mut symbols, &mut modes, file.clone(), false).run_all(lines)?;
+        let mut pass = Pass::new(self, &mut symbols, &mut modes, file, true);
+        pass.run_all(lines)?;
+        let (origin, bytes, segments) = pass.link()?;
+
+        Ok(Program {
+            origin,
+            bytes,
+            symbols: symbols
+                .into_iter()
+                .map(|(name, value)| (name, value as u16))
+                .collect(),
+            segments,
+        })
+    }
+
+    fn has_mode(&self, mnemonic: &str, mode: AddressingMode) -> bool {
+        self.opcode(mnemonic, mode).is_some()
+    }
+
+    pub(crate) fn opcode(&self, mnemonic: &str, mode: AddressingMode) -> Option<u8> {
+        // Shorten the key lifetime so a borrowed mnemonic can be looked up
+        let opcodes: &HashMap<(&str, AddressingMode), u8> = &self.opcodes;
+        opcodes.get(&(mnemonic, mode)).copied()
+    }
+
+    fn is_mnemonic(&self, mnemonic: &str) -> bool {
+        self.mnemonics.contains(mnemonic)
+    }
+}
+
+// Assembles with the NMOS 6502 instruction set
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    Assembler::new(Variant::default()).assemble(source)
+}
+
+#[derive(Debug, Clone)]
+struct SourceLine {
+    number: usize,
+    tokens: Vec<Spanned>,
+}
+
+fn tokenize_source(source: &str, file: Option<&Path>) -> Result<Vec<SourceLine>, AsmError> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| {
+            let tokens = lexer::tokenize(text, i + 1).map_err(|error| AsmError {
+                file: file.map(Path::to_path_buf),
+                ..error
+            })?;
+            Ok(SourceLine {
+                number: i + 1,
+                tokens,
+            })
+        })
+        .collect()
+}
+
+fn io_error(path: &Path, error: std::io::Error, at: Option<&Pass>) -> AsmError {
+    let kind = AsmErrorKind::Io {
+        path: path.display().to_string(),
+        message: error.to_string(),
+    };
+    match at {
+        Some(pass) => pass.error(kind),
+        // The file being assembled has no line to point at
+        None => AsmError {
+            file: Some(path.to_path_buf()),
+            ..AsmError::new(0, 0, kind)
+        },
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+    file: Option<PathBuf>,
+}
+
+// One arm of an `.if`
+struct Conditional {
+    // Whether the enclosing code is assembled at all
+    outer: bool,
+    taken: bool,
+    seen_else: bool,
+    line: usize,
+    column: usize,
+    file: Option<PathBuf>,
+}
+
+struct SegmentState {
+    name: String,
+    origin: Option<u16>,
+    pc: u16,
+    output: Vec<u8>,
+    // Where the segment was opened, for reporting overlaps
+    line: usize,
+    column: usize,
+    file: Option<PathBuf>,
+}
+
+struct Pass<'a> {
+    assembler: &'a Assembler,
+    symbols: &'a mut HashMap<String, i64>,
+    // The addressing mode of each instruction, chosen in the first pass
+    modes: &'a mut Vec<AddressingMode>,
+    // Each pass collects these again, so a macro is only known after its definition
+    macros: HashMap<String, Rc<Macro>>,
+    instruction: usize,
+    // Undefined symbols are errors in the final pass. Before that they only mean the
+    // value is not known yet.
+    final_pass: bool,
+    segments: Vec<SegmentState>,
+    segment: usize,
+    conditionals: Vec<Conditional>,
+    // Counts macro expansions, to give each its own scope for local labels
+    expansions: usize,
+    nesting: usize,
+    // The global label local labels belong to
+    scope: String,
+    file: Option<PathBuf>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Pass<'a> {
+    fn new(
+        assembler: &'a Assembler,
+        symbols: &'a mut HashMap<String, i64>,
+        modes: &'a mut Vec<AddressingMode>,
+        file: Option<PathBuf>,
+        final_pass: bool,
+    ) -> Self {
+        Pass {
+            assembler,
+            symbols,
+            modes,
+            macros: HashMap::new(),
+            instruction: 0,
+            final_pass,
+            segments: vec![SegmentState {
+                name: DEFAULT_SEGMENT.to_string(),
+                origin: None,
+                pc: DEFAULT_ORIGIN,
+                output: vec![],
+                line: 1,
+                column: 1,
+                file: file.clone(),
+            }],
+            segment: 0,
+            conditionals: vec![],
+            expansions: 0,
+            nesting: 0,
+            scope: String::new(),
+            file,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    fn run_all(&mut self, lines: &[SourceLine]) -> Result<(), AsmError> {
+        self.run(lines)?;
+
+        match self.conditionals.last() {
+            Some(conditional) => Err(AsmError {
+                file: conditional.file.clone(),
+                ..AsmError::new(
+                    conditional.line,
+                    conditional.column,
+                    AsmErrorKind::Unterminated(".if"),
+                )
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn run(&mut self, lines: &[SourceLine]) -> Result<(), AsmError> {
+        let mut index = 0;
+        while index < lines.len() {
+            let line = &lines[index];
+            index += 1;
+            self.line = line.number;
+            self.column = line.tokens.first().map_or(1, |spanned| spanned.column);
+
+            // Conditionals nest even where code is skipped, so they are tracked first
+            match parser::directive(&line.tokens) {
+                Some("else") | Some("endif") => {
+                    self.parse_and_run(&line.tokens)?;
+                    continue;
+                }
+                Some("if") if !self.active() => {
+                    self.push_conditional(false);
+                    continue;
+                }
+                _ if !self.active() => continue,
+                Some("macro") => {
+                    index = self.define_macro(lines, index)?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if let Some((name, args)) = self.macro_call(&line.tokens) {
+                let label =
+                    &line.tokens[..line.tokens.len() - parser::skip_label(&line.tokens).len()];
+                self.parse_and_run(label)?;
+                self.column = line.tokens[label.len()].column;
+                self.expand(&name, args)?;
+                continue;
+            }
+
+            self.parse_and_run(&line.tokens)?;
+        }
+        Ok(())
+    }
+
+    fn parse_and_run(&mut self, tokens: &[Spanned]) -> Result<(), AsmError> {
+        let line = parser::parse_line(tokens, self.line).map_err(|error| self.locate(error))?;
+
+        if let Some((label, column)) = &line.label {
+            self.column = *column;
+            let name = match label {
+                // A caller's local label passed to a macro, already qualified
+                Label::Global(name) if name.contains('@') => name.clone(),
+                Label::Global(name) => {
+                    self.scope = name.clone();
+                    name.clone()
+                }
+                Label::Local(name) => self.local_name(name),
+            };
+            self.define(name, self.pc() as i64)?;
+        }
+
+        if let Some((statement, column)) = &line.statement {
+            self.column = *column;
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn active(&self) -> bool {
+        self.conditionals
+            .last()
+            .is_none_or(|conditional| conditional.outer && conditional.taken)
+    }
+
+    fn push_conditional(&mut self, taken: bool) {
+        self.conditionals.push(Conditional {
+            outer: self.active(),
+            taken,
+            seen_else: false,
+            line: self.line,
+            column: self.column,
+            file: self.file.clone(),
+        });
+    }
+
+    // Collects the body of the macro started on the line before `index`, and returns
+    // the index of the line after its `.endmacro`
+    fn define_macro(&mut self, lines: &[SourceLine], index: usize) -> Result<usize, AsmError> {
+        let line = parser::parse_line(&lines[index - 1].tokens, self.line)
+            .map_err(|error| self.locate(error))?;
+        let (name, params) = match line.statement {
+            Some((Statement::Macro { name, params }, _)) => (name, params),
+            _ => unreachable!("the line starts with .macro"),
+        };
+        if line.label.is_some() {
+            return Err(self.error(AsmErrorKind::UnexpectedToken(String::from("label"))));
+        }
+
+        let mut end = index;
+        loop {
+            match lines
+                .get(end)
+                .and_then(|line| parser::directive(&line.tokens))
+            {
+                Some("endmacro") => break,
+                Some("macro") => {
+                    self.line = lines[end].number;
+                    return Err(self.error(AsmErrorKind::NestedMacro));
+                }
+                _ if end == lines.len() => {
+                    return Err(self.error(AsmErrorKind::Unterminated(".macro")));
+                }
+                _ => end += 1,
+            }
+        }
+
+        if self.macros.contains_key(&name) {
+            return Err(self.error(AsmErrorKind::DuplicateSymbol(name)));
+        }
+        self.macros.insert(
+            name,
+            Rc::new(Macro {
+                params,
+                body: lines[index..end].to_vec(),
+                file: self.file.clone(),
+            }),
+        );
+
+        Ok(end + 1)
+    }
+
+    // A macro name where a mnemonic would go, and the tokens of its arguments
+    fn macro_call<'t>(&self, tokens: &'t [Spanned]) -> Option<(String, &'t [Spanned])> {
+        let tokens = parser::skip_label(tokens);
+        match tokens.first() {
+            Some(Spanned {
+                token: Token::Identifier(name),
+                ..
+            }) if self.macros.contains_key(name) => Some((name.clone(), &tokens[1..])),
+            _ => None,
+        }
+    }
+
+    fn expand(&mut self, name: &str, args: &[Spanned]) -> Result<(), AsmError> {
+        let definition = Rc::clone(&self.macros[name]);
+
+        // Arguments are separated by commas outside parentheses, so `(a, b)` is one but
+        // `(ptr),Y` is two. An argument may be empty, as in `name , 1`.
+        let mut arguments: Vec<Vec<Spanned>> = vec![];
+        if !args.is_empty() {
+            arguments.push(vec![]);
+        }
+        let mut depth = 0;
+        for spanned in args {
+            match spanned.token {
+                Token::Punct(',') if depth == 0 => {
+                    arguments.push(vec![]);
+                    continue;
+                }
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') => depth -= 1,
+                _ => {}
+            }
+            arguments.last_mut().unwrap().push(spanned.clone());
+        }
+        if arguments.len() != definition.params.len() {
+            return Err(self.error(AsmErrorKind::WrongArgumentCount {
+                expected: definition.params.len(),
+                found: arguments.len(),
+            }));
+        }
+
+        let body: Vec<SourceLine> = definition
+            .body
+            .iter()
+            .map(|line| SourceLine {
+                number: line.number,
+                tokens: substitute(&line.tokens, &definition.params, &arguments, &self.scope),
+            })
+            .collect();
+
+        let call = MacroCall {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+        };
+        let scope = format!("{}#{}", name, self.expansions);
+        self.expansions += 1;
+        self.nested(definition.file.clone(), scope, |pass| pass.run(&body))
+            .map_err(|mut error| {
+                error.calls.push(call);
+                error
+            })
+    }
+
+    // Runs `f` as if in `file`, with its own scope for local labels
+    fn nested<F>(&mut self, file: Option<PathBuf>, scope: String, f: F) -> Result<(), AsmError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), AsmError>,
+    {
+        if self.nesting == MAX_NESTING {
+            return Err(self.error(AsmErrorKind::NestingTooDeep));
+        }
+
+        let saved = (
+            self.file.clone(),
+            self.scope.clone(),
+            self.line,
+            self.column,
+        );
+        self.nesting += 1;
+        self.file = file;
+        self.scope = scope;
+        let result = f(self);
+        self.nesting -= 1;
+        (self.file, self.scope, self.line, self.column) = saved;
+
+        result
+    }
+
+    // Relative to the file being assembled, if there is one
+    fn resolve(&self, path: &str) -> PathBuf {
+        match self.file.as_ref().and_then(|file| file.parent()) {
+            Some(directory) => directory.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+
+    fn include(&mut self, path: &str) -> Result<(), AsmError> {
+        let path = self.resolve(path);
+        let source =
+            fs::read_to_string(&path).map_err(|error| io_error(&path, error, Some(self)))?;
+        let lines = tokenize_source(&source, Some(&path))?;
+
+        // Local labels in the included file continue the current scope
+        let scope = self.scope.clone();
+        self.nested(Some(path), scope, |pass| pass.run(&lines))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), AsmError> {
+        match statement {
+            Statement::Instruction { mnemonic, operand } => self.instruction(mnemonic, operand),
+            Statement::Org(expr) => {
+                let origin = self.eval_now(expr)?;
+                if !(0..=0xFFFF).contains(&origin) {
+                    return Err(self.error(AsmErrorKind::ValueOutOfRange(origin)));
+                }
+                let origin = origin as u16;
+
+                let segment = &mut self.segments[self.segment];
+                match segment.origin {
+                    Some(_) if origin < segment.pc => {
+                        Err(self.error(AsmErrorKind::OriginBackwards(origin)))
+                    }
+                    Some(_) => {
+                        let gap = (origin - segment.pc) as usize;
+                        segment.output.resize(segment.output.len() + gap, 0);
+                        segment.pc = origin;
+                        Ok(())
+                    }
+                    None => {
+                        segment.pc = origin;
+                        Ok(())
+                    }
+                }
+            }
+            Statement::Byte(items) => {
+                for item in items {
+                    match item {
+                        DataItem::Str(text) => self.emit(text.as_bytes()),
+                        DataItem::Expr(expr) => {
+                            let value = self.eval(expr)?.unwrap_or(0);
+                            let value = self.check_range(value, -128, 0xFF)?;
+                            self.emit(&[value as u8]);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Statement::Word(exprs) => {
+                for expr in exprs {
+                    let value = self.eval(expr)?.unwrap_or(0);
+                    let value = self.check_range(value, -0x8000, 0xFFFF)?;
+                    self.emit(&(value as u16).to_le_bytes());
+                }
+                Ok(())
+            }
+            Statement::Assign { name, value } => {
+                if let Some(value) = self.eval(value)? {
+                    self.define(name.clone(), value)?;
+                }
+                Ok(())
+            }
+            // The count decides where everything after goes, so it must be known now
+            Statement::Reserve { count, value } => {
+                let count = self.eval_now(count)?;
+                if !(0..=0x10000).contains(&count) {
+                    return Err(self.error(AsmErrorKind::ValueOutOfRange(count)));
+                }
+                let value = match value {
+                    Some(value) => self.eval(value)?.unwrap_or(0),
+                    None => 0,
+                };
+                let value = self.check_range(value, -128, 0xFF)?;
+                self.emit(&vec![value as u8; count as usize]);
+                Ok(())
+            }
+            Statement::Segment(name) => {
+                self.switch_segment(name);
+                Ok(())
+            }
+            Statement::Include(path) => self.include(path),
+            Statement::Incbin(path) => {
+                let path = self.resolve(path);
+                let bytes = fs::read(&path).map_err(|error| io_error(&path, error, Some(self)))?;
+                self.emit(&bytes);
+                Ok(())
+            }
+            Statement::If(condition) => {
+                let taken = self.eval_now(condition)? != 0;
+                self.push_conditional(taken);
+                Ok(())
+            }
+            Statement::Else => match self.conditionals.last_mut() {
+                Some(conditional) if !conditional.seen_else => {
+                    conditional.taken = !conditional.taken;
+                    conditional.seen_else = true;
+                    Ok(())
+                }
+                _ => Err(self.error(AsmErrorKind::Unmatched(".else"))),
+            },
+            Statement::Endif => match self.conditionals.pop() {
+                Some(_) => Ok(()),
+                None => Err(self.error(AsmErrorKind::Unmatched(".endif"))),
+            },
+            // Definitions are collected by `run` before they get here
+            Statement::Macro { .. } => Err(self.error(AsmErrorKind::NestedMacro)),
+            Statement::Endmacro => Err(self.error(AsmErrorKind::Unmatched(".endmacro"))),
+        }
+    }
+
+    fn switch_segment(&mut self, name: &str) {
+        match self
+            .segments
+            .iter()
+            .position(|segment| segment.name == name)
+        {
+            Some(index) => self.segment = index,
+            None => {
+                let pc = self.pc();
+                self.segments.push(SegmentState {
+                    name: name.to_string(),
+                    origin: None,
+                    pc,
+                    output: vec![],
+                    line: self.line,
+                    column: self.column,
+                    file: self.file.clone(),
+                });
+                self.segment = self.segments.len() - 1;
+            }
+        }
+    }
+
+    // Lays the segments out in one image, and returns its origin, the image and the
+    // segments that hold anything
+    fn link(&mut self) -> Result<(u16, Vec<u8>, Vec<Segment>), AsmError> {
+        let mut segments: Vec<&SegmentState> = self
+            .segments
+            .iter()
+            .filter(|segment| !segment.output.is_empty())
+            .collect();
+        segments.sort_by_key(|segment| segment.origin);
+
+        for pair in segments.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            let end = first.origin.unwrap() as usize + first.output.len();
+            if second.origin.unwrap() as usize >= end {
+                continue;
+            }
+            return Err(AsmError {
+                file: second.file.clone(),
+                ..AsmError::new(
+                    second.line,
+                    second.column,
+                    AsmErrorKind::OverlappingSegments(first.name.clone(), second.name.clone()),
+                )
+            });
+        }
+
+        let origin = segments
+            .first()
+            .map_or(DEFAULT_ORIGIN, |segment| segment.origin.unwrap());
+        let mut bytes = vec![];
+        for segment in &segments {
+            let start = (segment.origin.unwrap() - origin) as usize;
+            bytes.resize(start, 0);
+            bytes.extend_from_slice(&segment.output);
+        }
+
+        let segments = self
+            .segments
+            .iter()
+            .filter(|segment| !segment.output.is_empty())
+            .map(|segment| Segment {
+                name: segment.name.clone(),
+                origin: segment.origin.unwrap(),
+                bytes: segment.output.clone(),
+            })
+            .collect();
+
+        Ok((origin, bytes, segments))
+    }
+
+    fn instruction(&mut self, mnemonic: &str, operand: &Operand) -> Result<(), AsmError> {
+        if !self.assembler.is_mnemonic(mnemonic) {
+            return Err(self.error(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())));
+        }
+
+        let mode = if self.final_pass {
+            *self
+                .modes
+                .get(self.instruction)
+                .ok_or_else(|| self.error(AsmErrorKind::PhaseError))?
+        } else {
+            let mode = self.choose_mode(mnemonic, operand)?;
+            self.modes.push(mode);
+            mode
+        };
+        self.instruction += 1;
+
+        let opcode = self.assembler.opcode(mnemonic, mode).ok_or_else(|| {
+            self.error(AsmErrorKind::InvalidAddressingMode {
+                mnemonic: mnemonic.to_string(),
+                mode: describe_mode(mode).to_string(),
+            })
+        })?;
+
+        let mut bytes = vec![opcode];
+        match operand {
+            Operand::None | Operand::Accumulator => {}
+            Operand::Pair(zero_page, target) => {
+                let zero_page = self.eval(zero_page)?.unwrap_or(0);
+                bytes.push(self.check_range(zero_page, 0, 0xFF)? as u8);
+                bytes.push(self.branch_offset(target, 3)?);
+            }
+            Operand::Direct(expr) if mode == AddressingMode::Relative => {
+                bytes.push(self.branch_offset(expr, 2)?);
+            }
+            Operand::Immediate(expr) => {
+                let value = self.eval(expr)?.unwrap_or(0);
+                bytes.push(self.check_range(value, -128, 0xFF)? as u8);
+            }
+            Operand::Direct(expr)
+            | Operand::IndexedX(expr)
+            | Operand::IndexedY(expr)
+            | Operand::Indirect(expr)
+            | Operand::IndirectX(expr)
+            | Operand::IndirectY(expr) => {
+                let value = self.eval(expr)?.unwrap_or(0);
+                if mode.operand_length() == 1 {
+                    bytes.push(self.check_range(value, 0, 0xFF)? as u8);
+                } else {
+                    let value = self.check_range(value, 0, 0xFFFF)? as u16;
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+
+        self.emit(&bytes);
+        Ok(())
+    }
+
+    fn choose_mode(&self, mnemonic: &str, operand: &Operand) -> Result<AddressingMode, AsmError> {
+        let has = |mode| self.assembler.has_mode(mnemonic, mode);
+
+        let mode = match operand {
+            // `ASL` is short for `ASL A`
+            Operand::None if has(AddressingMode::Accumulator) => AddressingMode::Accumulator,
+            Operand::None => AddressingMode::Implicit,
+            Operand::Accumulator => AddressingMode::Accumulator,
+            Operand::Immediate(_) => AddressingMode::Immediate,
+            Operand::Pair(..) => AddressingMode::ZeroPageRelative,
+            Operand::Direct(_) if has(AddressingMode::Relative) => AddressingMode::Relative,
+            Operand::Direct(expr) => self.zero_page_or(
+                mnemonic,
+                expr,
+                AddressingMode::ZeroPage,
+                AddressingMode::Absolute,
+            )?,
+            Operand::IndexedX(expr) => self.zero_page_or(
+                mnemonic,
+                expr,
+                AddressingMode::ZeroPageX,
+                AddressingMode::AbsoluteX,
+            )?,
+            Operand::IndexedY(expr) => self.zero_page_or(
+                mnemonic,
+                expr,
+                AddressingMode::ZeroPageY,
+                AddressingMode::AbsoluteY,
+            )?,
+            Operand::Indirect(_) if has(AddressingMode::Indirect) => AddressingMode::Indirect,
+            Operand::Indirect(_) => AddressingMode::ZeroPageIndirect,
+            Operand::IndirectX(_) if has(AddressingMode::AbsoluteIndexedIndirect) => {
+                AddressingMode::AbsoluteIndexedIndirect
+            }
+            Operand::IndirectX(_) => AddressingMode::IndirectX,
+            Operand::IndirectY(_) => AddressingMode::IndirectY,
+        };
+        Ok(mode)
+    }
+
+    // Zero page addressing needs the value to be known in the first pass. Forward
+    // references get the absolute form, which fits whatever they turn out to be.
+    // Instructions like STX zp,Y only have the zero page form, so they get it either way.
+    fn zero_page_or(
+        &self,
+        mnemonic: &str,
+        expr: &Expr,
+        zero_page: AddressingMode,
+        absolute: AddressingMode,
+    ) -> Result<AddressingMode, AsmError> {
+        let fits = match self.eval(expr)? {
+            Some(value) => (0..=0xFF).contains(&value) && !expr.is_wide(),
+            None => false,
+        };
+        let has = |mode| self.assembler.has_mode(mnemonic, mode);
+
+        if (fits && has(zero_page)) || !has(absolute) {
+            Ok(zero_page)
+        } else {
+            Ok(absolute)
+        }
+    }
+
+    // Offsets count from the end of the instruction
+    fn branch_offset(&self, target: &Expr, length: u16) -> Result<u8, AsmError> {
+        let target = match self.eval(target)? {
+            Some(target) => target,
+            None => return Ok(0),
+        };
+        let offset = target - self.pc().wrapping_add(length) as i64;
+        if self.final_pass && !(-128..=127).contains(&offset) {
+            return Err(self.error(AsmErrorKind::BranchOutOfRange(offset)));
+        }
+        Ok(offset as u8)
+    }
+
+    // Returns None for a symbol that is not defined yet, before the final pass
+    fn eval(&self, expr: &Expr) -> Result<Option<i64>, AsmError> {
+        self.eval_with(expr, self.final_pass)
+    }
+
+    // Evaluates an expression whose value is needed right away, like that of `.org`
+    fn eval_now(&self, expr: &Expr) -> Result<i64, AsmError> {
+        Ok(self.eval_with(expr, true)?.unwrap())
+    }
+
+    // With `strict` set, undefined symbols are errors
+    fn eval_with(&self, expr: &Expr, strict: bool) -> Result<Option<i64>, AsmError> {
+        let value = match expr {
+            Expr::Number { value, .. } => *value,
+            Expr::Current => self.pc() as i64,
+            Expr::Symbol { name, column } => match self.lookup(name.clone(), *column, strict)? {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            Expr::Local { name, column } => {
+                let name = self.local_name(name);
+                match self.lookup(name, *column, strict)? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                }
+            }
+            Expr::Unary(op, expr) => {
+                let value = match self.eval_with(expr, strict)? {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                match op {
+                    '-' => value.wrapping_neg(),
+                    '~' => !value,
+                    '<' => value & 0xFF,
+                    _ => (value >> 8) & 0xFF,
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let (left, right) = match (
+                    self.eval_with(left, strict)?,
+                    self.eval_with(right, strict)?,
+                ) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => return Ok(None),
+                };
+                match op {
+                    BinaryOp::Or => left | right,
+                    BinaryOp::Xor => left ^ right,
+                    BinaryOp::And => left & right,
+                    BinaryOp::ShiftLeft => left.checked_shl(right as u32).unwrap_or(0),
+                    BinaryOp::ShiftRight => left.checked_shr(right as u32).unwrap_or(0),
+                    BinaryOp::Add => left.wrapping_add(right),
+                    BinaryOp::Subtract => left.wrapping_sub(right),
+                    BinaryOp::Multiply => left.wrapping_mul(right),
+                    BinaryOp::Divide if right == 0 => {
+                        return Err(self.error(AsmErrorKind::DivisionByZero))
+                    }
+                    BinaryOp::Divide => left.wrapping_div(right),
+                }
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn lookup(&self, name: String, column: usize, strict: bool) -> Result<Option<i64>, AsmError> {
+        match self.symbols.get(&name) {
+            Some(value) => Ok(Some(*value)),
+            None if strict => Err(self.locate(AsmError::new(
+                self.line,
+                column,
+                AsmErrorKind::UndefinedSymbol(name),
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    // Symbols are defined again in the final pass, with the same values
+    fn define(&mut self, name: String, value: i64) -> Result<(), AsmError> {
+        if !self.final_pass && self.symbols.contains_key(&name) {
+            return Err(self.error(AsmErrorKind::DuplicateSymbol(name)));
+        }
+        self.symbols.insert(name, value);
+        Ok(())
+    }
+
+    fn local_name(&self, name: &str) -> String {
+        format!("{}@{}", self.scope, name)
+    }
+
+    fn pc(&self) -> u16 {
+        self.segments[self.segment].pc
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        let segment = &mut self.segments[self.segment];
+        segment.origin.get_or_insert(segment.pc);
+        segment.output.extend_from_slice(bytes);
+        segment.pc = segment.pc.wrapping_add(bytes.len() as u16);
+    }
+
+    // Values are only checked once they are final
+    fn check_range(&self, value: i64, min: i64, max: i64) -> Result<i64, AsmError> {
+        if self.final_pass && !(min..=max).contains(&value) {
+            return Err(self.error(AsmErrorKind::ValueOutOfRange(value)));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, kind: AsmErrorKind) -> AsmError {
+        self.locate(AsmError::new(self.line, self.column, kind))
+    }
+
+    // Errors from the lexer and parser only know their line and column
+    fn locate(&self, error: AsmError) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            ..error
+        }
+    }
+}
+
+// Replaces macro parameters with the tokens of their arguments. Local labels in the
+// arguments belong to the caller's scope, so they are qualified with it here.
+fn substitute(
+    tokens: &[Spanned],
+    params: &[String],
+    arguments: &[Vec<Spanned>],
+    scope: &str,
+) -> Vec<Spanned> {
+    let mut result = vec![];
+    for spanned in tokens {
+        let argument = match &spanned.token {
+            Token::Identifier(name) => params.iter().position(|param| param == name),
+            _ => None,
+        };
+        match argument {
+            // Errors inside an argument point at the parameter in the body
+            Some(index) => result.extend(arguments[index].iter().map(|argument| Spanned {
+                token: match &argument.token {
+                    Token::Local(name) => Token::Identifier(format!("{}@{}", scope, name)),
+                    token => token.clone(),
+                },
+                column: spanned.column,
+            })),
+            None => result.push(spanned.clone()),
+        }
+    }
+    result
+}
+
+// How an addressing mode is named in error messages
+fn describe_mode(mode: AddressingMode) -> &'static str {
+    match mode {
+        AddressingMode::Implicit => "implied",
+        AddressingMode::Accumulator => "accumulator",
+        AddressingMode::Immediate => "immediate",
+        AddressingMode::ZeroPage => "zero page",
+        AddressingMode::ZeroPageX => "zero page,X",
+        AddressingMode::ZeroPageY => "zero page,Y",
+        AddressingMode::Relative => "relative",
+        AddressingMode::Absolute => "absolute",
+        AddressingMode::AbsoluteX => "absolute,X",
+        AddressingMode::AbsoluteY => "absolute,Y",
+        AddressingMode::Indirect => "indirect",
+        AddressingMode::IndirectX => "(zero page,X)",
+        AddressingMode::IndirectY => "(zero page),Y",
+        AddressingMode::ZeroPageIndirect => "(zero page)",
+        AddressingMode::AbsoluteIndexedIndirect => "(absolute,X)",
+        AddressingMode::ZeroPageRelative => "zero page,relative",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cpu::CPU;
+    use crate::disasm::flow::FlowDisassembler;
+
+    fn bytes(source: &str) -> Vec<u8> {
+        assemble(source).unwrap().bytes
+    }
+
+    fn error(source: &str) -> AsmError {
+        assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn test_runs_on_cpu() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(bytes("LDA #$05\nTAX\nBRK")).unwrap();
+        assert_eq!(cpu.register_x, 0x05);
+    }
+
+    #[test]
+    fn test_addressing_modes() {
+        let source = "
+            LDA #$10
+            LDA $10
+            LDA $10,X
+            LDX $10,Y
+            LDA $1234
+            LDA $1234,X
+            LDA $1234,Y
+            LDA ($10,X)
+            LDA ($10),Y
+            JMP ($1234)
+            ASL A
+            ASL
+            INX
+        ";
+        assert_eq!(
+            bytes(source),
+            vec![
+                0xa9, 0x10, 0xa5, 0x10, 0xb5, 0x10, 0xb6, 0x10, 0xad, 0x34, 0x12, 0xbd, 0x34, 0x12,
+                0xb9, 0x34, 0x12, 0xa1, 0x10, 0xb1, 0x10, 0x6c, 0x34, 0x12, 0x0a, 0x0a, 0xe8,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_page_or_absolute() {
+        let source = "
+            zp = $20
+            LDA zp          ; known and small: zero page
+            LDA $0020       ; written wide: absolute
+            LDA later       ; forward reference: absolute
+            LDA $20,Y       ; LDA has no zero page,Y form
+            later = $30
+        ";
+        assert_eq!(
+            bytes(source),
+            vec![0xa5, 0x20, 0xad, 0x20, 0x00, 0xad, 0x30, 0x00, 0xb9, 0x20, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_labels_and_branches() {
+        let program = assemble(
+            "
+            .org $C000
+    start:  LDX #3
+    loop:   DEX
+            BNE loop
+            BEQ done
+            NOP
+    done:   JMP start
+        ",
+        )
+        .unwrap();
+        assert_eq!(program.origin, 0xC000);
+        assert_eq!(
+            program.bytes,
+            vec![0xa2, 0x03, 0xca, 0xd0, 0xfd, 0xf0, 0x01, 0xea, 0x4c, 0x00, 0xc0]
+        );
+        assert_eq!(program.symbols["start"], 0xC000);
+        assert_eq!(program.symbols["done"], 0xC008);
+    }
+
+    #[test]
+    fn test_local_labels() {
+        let program = assemble(
+            "
+    first:  LDY #2
+    @loop:  DEY
+            BNE @loop
+    second: LDY #2
+    @loop:  DEY
+            BNE @loop
+        ",
+        )
+        .unwrap();
+        assert_eq!(program.symbols["first@loop"], 0x8002);
+        assert_eq!(program.symbols["second@loop"], 0x8007);
+        assert_eq!(&program.bytes[3..5], &[0xd0, 0xfd]);
+        assert_eq!(&program.bytes[8..10], &[0xd0, 0xfd]);
+    }
+
+    #[test]
+    fn test_expressions() {
+        let source = "
+            .org $1234
+    here:   .byte <here, >here, 1+2*3, (1+2)*3, %1010 | 1, 'A', -1
+            .word * - 2, $FF << 4, here / 2
+            LDA (1+2)*3
+        ";
+        assert_eq!(
+            bytes(source),
+            vec![
+                0x34, 0x12, 0x07, 0x09, 0x0b, 0x41, 0xff, 0x39, 0x12, 0xf0, 0x0f, 0x1a, 0x09, 0xa5,
+                0x09,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_and_org_gaps() {
+        let program = assemble(
+            "
+            .org $8000
+            .byte \"Hi\", 0
+            .org $8005
+    vec:    .word vec, $ABCD
+        ",
+        )
+        .unwrap();
+        assert_eq!(
+            program.bytes,
+            vec![0x48, 0x69, 0x00, 0x00, 0x00, 0x05, 0x80, 0xcd, 0xab]
+        );
+    }
+
+    #[test]
+    fn test_variants() {
+        let source = "
+            LDA ($20)
+            JMP ($1234,X)
+            STZ $10
+    here:   BBR0 $10,here
+        ";
+        assert_eq!(
+            Assembler::new(Variant::Wdc65C02)
+                .assemble(source)
+                .unwrap()
+                .bytes,
+            vec![0xb2, 0x20, 0x7c, 0x34, 0x12, 0x64, 0x10, 0x0f, 0x10, 0xfd]
+        );
+
+        let error = error("STZ $10");
+        assert_eq!(
+            error.kind,
+            AsmErrorKind::UnknownMnemonic(String::from("STZ"))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("  NOP\n  LDQ #1"),
+            AsmError::new(2, 3, AsmErrorKind::UnknownMnemonic(String::from("LDQ")))
+        );
+        assert_eq!(
+            error("  LDA missing"),
+            AsmError::new(1, 7, AsmErrorKind::UndefinedSymbol(String::from("missing")))
+        );
+        assert_eq!(
+            error("  STA #1"),
+            AsmError::new(
+                1,
+                3,
+                AsmErrorKind::InvalidAddressingMode {
+                    mnemonic: String::from("STA"),
+                    mode: String::from("immediate")
+                }
+            )
+        );
+        assert_eq!(
+            error("a: NOP\na: NOP"),
+            AsmError::new(2, 1, AsmErrorKind::DuplicateSymbol(String::from("a")))
+        );
+        assert_eq!(
+            error("  LDA #1 ?"),
+            AsmError::new(1, 10, AsmErrorKind::UnexpectedCharacter('?'))
+        );
+        assert_eq!(
+            error("  LDA #$100"),
+            AsmError::new(1, 3, AsmErrorKind::ValueOutOfRange(0x100))
+        );
+        assert_eq!(
+            error("  .org $9000\n  NOP\n  .org $8000"),
+            AsmError::new(3, 3, AsmErrorKind::OriginBackwards(0x8000))
+        );
+        assert_eq!(
+            error("  .repeat 3"),
+            AsmError::new(1, 3, AsmErrorKind::UnknownDirective(String::from("repeat")))
+        );
+        assert_eq!(
+            error("  LDA #1 ?").to_string(),
+            "1:10: unexpected character '?'"
+        );
+    }
+
+    #[test]
+    fn test_branch_out_of_range() {
+        let mut source = String::from("start: NOP\n");
+        for _ in 0..130 {
+            source.push_str("  NOP\n");
+        }
+        source.push_str("  BNE start\n");
+        assert_eq!(error(&source).kind, AsmErrorKind::BranchOutOfRange(-133));
+    }
+
+    #[test]
+    fn test_reassembles_flow_listing() {
+        let source = "
+            .org $FFE0
+    reset:  LDX #$00
+    loop:   LDA table,X
+            STA $0200,X
+            JSR sub
+            INX
+            BNE loop
+            JMP ($0300)
+    sub:    ROL A
+            RTS
+    table:  .byte $01, $02, $03
+            .word reset, reset, sub
+        ";
+        let program = assemble(source).unwrap();
+        let listing = FlowDisassembler::new(&program.bytes, program.origin, Variant::Nmos6502)
+            .run()
+            .listing();
+        assert_eq!(assemble(&listing).unwrap().bytes, program.bytes);
+    }
+
+    #[test]
+    fn test_macros() {
+        let program = assemble(
+            "
+            .macro load_store value, address
+            LDA #value
+            STA address
+            .endmacro
+
+            .macro wait count
+            LDX #count
+    @loop:  DEX
+            BNE @loop
+            .endmacro
+
+    start:  load_store $01, $0200
+            load_store <start, $10
+            wait 2
+            wait 3
+            BRK
+        ",
+        )
+        .unwrap();
+        assert_eq!(
+            program.bytes,
+            vec![
+                0xa9, 0x01, 0x8d, 0x00, 0x02, 0xa9, 0x00, 0x85, 0x10, 0xa2, 0x02, 0xca, 0xd0, 0xfd,
+                0xa2, 0x03, 0xca, 0xd0, 0xfd, 0x00,
+            ]
+        );
+        // Each expansion has its own scope for local labels, numbered across all macros
+        assert_eq!(program.symbols["wait#2@loop"], 0x800B);
+        assert_eq!(program.symbols["wait#3@loop"], 0x8010);
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        assert_eq!(
+            error(".macro one a\n  LDA #a\n.endmacro\n  one 1, 2"),
+            AsmError::new(
+                4,
+                3,
+                AsmErrorKind::WrongArgumentCount {
+                    expected: 1,
+                    found: 2
+                }
+            )
+        );
+        assert_eq!(
+            error("  NOP\n.macro open\n  NOP"),
+            AsmError::new(2, 1, AsmErrorKind::Unterminated(".macro"))
+        );
+        assert_eq!(
+            error(".macro outer\n.macro inner\n.endmacro"),
+            AsmError::new(2, 1, AsmErrorKind::NestedMacro)
+        );
+        assert_eq!(
+            error(".macro again\n  again\n.endmacro\n  again").kind,
+            AsmErrorKind::NestingTooDeep
+        );
+        assert_eq!(
+            error("  .endmacro"),
+            AsmError::new(1, 3, AsmErrorKind::Unmatched(".endmacro"))
+        );
+    }
+
+    #[test]
+    fn test_macro_error_call_sites() {
+        let error = error(".macro load v\n  LDA #v\n.endmacro\n.macro twice v\n  load v\n.endmacro\n  NOP\n  twice missing");
+        assert_eq!((error.line, error.column), (2, 8));
+        assert_eq!(
+            error.calls,
+            vec![
+                MacroCall {
+                    file: None,
+                    line: 5,
+                    column: 3
+                },
+                MacroCall {
+                    file: None,
+                    line: 8,
+                    column: 3
+                },
+            ]
+        );
+        assert_eq!(
+            error.to_string(),
+            "2:8: undefined symbol 'missing', in macro called at 5:3, in macro called at 8:3"
+        );
+    }
+
+    #[test]
+    fn test_local_label_macro_arguments() {
+        // Local labels in arguments belong to the scope of the call
+        let source = "
+start:
+.macro jmpto target
+  JMP target
+@here:
+.endmacro
+  jmpto @here
+@here: NOP
+";
+        let program = assemble(source).unwrap();
+        assert_eq!(program.bytes, vec![0x4c, 0x03, 0x80, 0xea]);
+        assert_eq!(program.symbols["start@here"], 0x8003);
+        assert_eq!(program.symbols["jmpto#0@here"], 0x8003);
+    }
+
+    #[test]
+    fn test_empty_macro_arguments() {
+        let source = ".macro pair a, b\n  .byte a b\n.endmacro\n  pair , 1\n  pair 2,\n";
+        assert_eq!(bytes(source), vec![1, 2]);
+        assert_eq!(
+            error(".macro pair a, b\n.endmacro\n  pair"),
+            AsmError::new(
+                3,
+                3,
+                AsmErrorKind::WrongArgumentCount {
+                    expected: 2,
+                    found: 0
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_macro_defined_after_use() {
+        // A macro only replaces mnemonics below its definition, in both passes
+        let source = "  nop\n.macro nop\n  INX\n  INX\n.endmacro\n  nop\n";
+        assert_eq!(bytes(source), vec![0xea, 0xe8, 0xe8]);
+        assert_eq!(
+            error(".macro twice\n  NOP\n.endmacro\n.macro twice\n.endmacro"),
+            AsmError::new(4, 1, AsmErrorKind::DuplicateSymbol(String::from("twice")))
+        );
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let source = "
+            debug = 1
+            .if debug
+            LDA #1
+            .if debug - 1
+            LDA #2
+            .else
+            LDA #3
+            .endif
+            .else
+            LDA #4
+            .if 1
+            this would not parse
+            .endif
+            .endif
+            .if 0
+            .else
+            LDA #5
+            .endif
+        ";
+        assert_eq!(bytes(source), vec![0xa9, 0x01, 0xa9, 0x03, 0xa9, 0x05]);
+    }
+
+    #[test]
+    fn test_conditional_errors() {
+        assert_eq!(
+            error("  .if 1\n  NOP"),
+            AsmError::new(1, 3, AsmErrorKind::Unterminated(".if"))
+        );
+        assert_eq!(
+            error("  .else"),
+            AsmError::new(1, 3, AsmErrorKind::Unmatched(".else"))
+        );
+        assert_eq!(
+            error("  .if 1\n  .else\n  .else\n  .endif"),
+            AsmError::new(3, 3, AsmErrorKind::Unmatched(".else"))
+        );
+        assert_eq!(
+            error("  .endif"),
+            AsmError::new(1, 3, AsmErrorKind::Unmatched(".endif"))
+        );
+        // The condition is needed in the first pass
+        assert_eq!(
+            error("  .if later\n  .endif\nlater = 1"),
+            AsmError::new(1, 7, AsmErrorKind::UndefinedSymbol(String::from("later")))
+        );
+    }
+
+    #[test]
+    fn test_reserve() {
+        let program = assemble(
+            "
+    buffer: .res 3
+            .fill 2, $EA
+    after:  NOP
+        ",
+        )
+        .unwrap();
+        assert_eq!(program.bytes, vec![0, 0, 0, 0xea, 0xea, 0xea]);
+        assert_eq!(program.symbols["after"], 0x8005);
+    }
+
+    #[test]
+    fn test_segments() {
+        let program = assemble(
+            "
+            .segment \"DATA\"
+            .org $9000
+    table:  .byte 1, 2
+            .segment \"CODE\"
+            LDA table
+            .segment \"DATA\"
+            .byte 3
+            .segment \"RODATA\"
+            .byte 4
+        ",
+        )
+        .unwrap();
+        assert_eq!(program.origin, 0x8000);
+        assert_eq!(
+            program.segments,
+            vec![
+                Segment {
+                    name: String::from("CODE"),
+                    origin: 0x8000,
+                    bytes: vec![0xad, 0x00, 0x90],
+                },
+                Segment {
+                    name: String::from("DATA"),
+                    origin: 0x9000,
+                    bytes: vec![1, 2, 3],
+                },
+                // Opened after DATA, so it starts where DATA ends
+                Segment {
+                    name: String::from("RODATA"),
+                    origin: 0x9003,
+                    bytes: vec![4],
+                },
+            ]
+        );
+        assert_eq!(program.bytes.len(), 0x1004);
+        assert_eq!(&program.bytes[0x1000..], &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_overlapping_segments() {
+        assert_eq!(
+            error("  NOP\n  .segment \"DATA\"\n  .org $8000\n  NOP"),
+            AsmError::new(
+                2,
+                3,
+                AsmErrorKind::OverlappingSegments(String::from("CODE"), String::from("DATA"))
+            )
+        );
+    }
+
+    // A scratch directory for tests that read files
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("cpu_6502_{}_{}", name, std::process::id()));
+        fs::create_dir_all(directory.join("lib")).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_include_and_incbin() {
+        let directory = scratch_directory("include");
+        fs::write(
+            directory.join("main.s"),
+            "  .include \"lib/macros.s\"\n  store 7\n  .incbin \"lib/data.bin\"\n",
+        )
+        .unwrap();
+        // Paths in an included file are relative to that file
+        fs::write(
+            directory.join("lib/macros.s"),
+            ".macro store value\n  LDA #value\n  STA $10\n.endmacro\n  .include \"more.s\"\n",
+        )
+        .unwrap();
+        fs::write(directory.join("lib/more.s"), "  NOP\n").unwrap();
+        fs::write(directory.join("lib/data.bin"), [0xde, 0xad]).unwrap();
+
+        let program = Assembler::new(Variant::Nmos6502)
+            .assemble_file(directory.join("main.s"))
+            .unwrap();
+        assert_eq!(
+            program.bytes,
+            vec![0xea, 0xa9, 0x07, 0x85, 0x10, 0xde, 0xad]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let directory = scratch_directory("include_errors");
+        fs::write(directory.join("main.s"), "  NOP\n  .include \"bad.s\"\n").unwrap();
+        fs::write(directory.join("bad.s"), "  NOP\n  LDQ #1\n").unwrap();
+
+        let assembler = Assembler::new(Variant::Nmos6502);
+        let error = assembler
+            .assemble_file(directory.join("main.s"))
+            .unwrap_err();
+        assert_eq!(error.file, Some(directory.join("bad.s")));
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{}:2:3: unknown mnemonic 'LDQ'",
+                directory.join("bad.s").display()
+            )
+        );
+
+        fs::write(directory.join("main.s"), "  .incbin \"missing.bin\"\n").unwrap();
+        let error = assembler
+            .assemble_file(directory.join("main.s"))
+            .unwrap_err();
+        assert_eq!(error.file, Some(directory.join("main.s")));
+        assert!(matches!(error.kind, AsmErrorKind::Io { .. }));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnexpectedCharacter(char),
+    // Describes what was found instead
+    UnexpectedToken(String),
+    // Describes what was expected
+    Expected(&'static str),
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    // The instruction has no opcode for the addressing mode its operand implies
+    InvalidAddressingMode { mnemonic: String, mode: String },
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    ValueOutOfRange(i64),
+    BranchOutOfRange(i64),
+    DivisionByZero,
+    // `.org` may only move forward once code has been emitted
+    OriginBackwards(u16),
+    // A block directive like `.if` without its end
+    Unterminated(&'static str),
+    // An end or `.else` without the directive that opens its block
+    Unmatched(&'static str),
+    NestedMacro,
+    WrongArgumentCount { expected: usize, found: usize },
+    // Includes or macro expansions nested beyond the limit, usually by recursion
+    NestingTooDeep,
+    OverlappingSegments(String, String),
+    // An include or incbin that could not be read
+    Io { path: String, message: String },
+    // The final pass met instructions the first pass did not, so the sizes chosen in
+    // the first pass no longer apply
+    PhaseError,
+}
+
+// Lines and columns count from one. `file` is set for errors in source read from a
+// file, including through `.include`. Errors inside a macro expansion point at the
+// line of the macro body, and `calls` holds the calls that expanded it, innermost
+// first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub kind: AsmErrorKind,
+    pub calls: Vec<MacroCall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroCall {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl AsmError {
+    pub fn new(line: usize, column: usize, kind: AsmErrorKind) -> Self {
+        AsmError {
+            file: None,
+            line,
+            column,
+            kind,
+            calls: vec![],
+        }
+    }
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            AsmErrorKind::UnexpectedToken(found) => write!(f, "unexpected {}", found),
+            AsmErrorKind::Expected(expected) => write!(f, "expected {}", expected),
+            AsmErrorKind::UnknownMnemonic(mnemonic) => {
+                write!(f, "unknown mnemonic '{}'", mnemonic)
+            }
+            AsmErrorKind::UnknownDirective(directive) => {
+                write!(f, "unknown directive '.{}'", directive)
+            }
+            AsmErrorKind::InvalidAddressingMode { mnemonic, mode } => {
+                write!(f, "{} does not support {} addressing", mnemonic, mode)
+            }
+            AsmErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol '{}'", name),
+            AsmErrorKind::DuplicateSymbol(name) => write!(f, "symbol '{}' already defined", name),
+            AsmErrorKind::ValueOutOfRange(value) => write!(f, "value {} out of range", value),
+            AsmErrorKind::BranchOutOfRange(offset) => {
+                write!(f, "branch offset {} out of range", offset)
+            }
+            AsmErrorKind::DivisionByZero => write!(f, "division by zero"),
+            AsmErrorKind::OriginBackwards(origin) => {
+                write!(f, ".org ${:04X} is behind the code already emitted", origin)
+            }
+            AsmErrorKind::Unterminated(directive) => write!(f, "{} is never closed", directive),
+            AsmErrorKind::Unmatched(directive) => {
+                write!(f, "{} without a matching opening directive", directive)
+            }
+            AsmErrorKind::NestedMacro => write!(f, "macros cannot be defined inside macros"),
+            AsmErrorKind::WrongArgumentCount { expected, found } => {
+                write!(f, "expected {} macro arguments, found {}", expected, found)
+            }
+            AsmErrorKind::NestingTooDeep => {
+                write!(f, "includes or macros nested too deeply")
+            }
+            AsmErrorKind::OverlappingSegments(first, second) => {
+                write!(f, "segment {} overlaps segment {}", second, first)
+            }
+            AsmErrorKind::Io { path, message } => {
+                write!(f, "cannot read {}: {}", path, message)
+            }
+            AsmErrorKind::PhaseError => {
+                write!(f, "instruction not seen in the first pass")
+            }
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)?;
+        for call in &self.calls {
+            write!(f, ", in macro called at ")?;
+            if let Some(file) = &call.file {
+                write!(f, "{}:", file.display())?;
+            }
+            write!(f, "{}:{}", call.line, call.column)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AsmError {}
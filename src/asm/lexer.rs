@@ -0,0 +1,156 @@
+// Splits a source line into tokens. Comments start with `;` and run to the end of the
+// line.
+
+use super::error::{AsmError, AsmErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Identifier(String),
+    // `@name`, scoped to the preceding global label
+    Local(String),
+    // `.name`, lowercased, without the dot
+    Directive(String),
+    // `wide` is set for hex literals with more than two digits, like `$0010`, which
+    // force absolute addressing
+    Number { value: i64, wide: bool },
+    Str(String),
+    Punct(char),
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl Token {
+    // How the token is described in error messages
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Identifier(name) => format!("'{}'", name),
+            Token::Local(name) => format!("'@{}'", name),
+            Token::Directive(name) => format!("'.{}'", name),
+            Token::Number { value, .. } => format!("number {}", value),
+            Token::Str(_) => String::from("string"),
+            Token::Punct(c) => format!("'{}'", c),
+            Token::ShiftLeft => String::from("'<<'"),
+            Token::ShiftRight => String::from("'>>'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned {
+    pub token: Token,
+    pub column: usize,
+}
+
+pub fn tokenize(source: &str, line: usize) -> Result<Vec<Spanned>, AsmError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let error = |kind| AsmError::new(line, column, kind);
+
+        let token = match c {
+            ';' => break,
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '$' => {
+                let digits = take_while(&chars, i + 1, |c| c.is_ascii_hexdigit());
+                i += 1 + digits.len();
+                let value = i64::from_str_radix(&digits, 16)
+                    .map_err(|_| error(AsmErrorKind::Expected("hexadecimal digits")))?;
+                Token::Number {
+                    value,
+                    wide: digits.len() > 2,
+                }
+            }
+            '%' if chars.get(i + 1).is_some_and(|c| *c == '0' || *c == '1') => {
+                let digits = take_while(&chars, i + 1, |c| c == '0' || c == '1');
+                i += 1 + digits.len();
+                let value = i64::from_str_radix(&digits, 2)
+                    .map_err(|_| error(AsmErrorKind::ValueOutOfRange(0)))?;
+                Token::Number { value, wide: false }
+            }
+            '0'..='9' => {
+                let digits = take_while(&chars, i, |c| c.is_ascii_digit());
+                i += digits.len();
+                let value = digits
+                    .parse()
+                    .map_err(|_| error(AsmErrorKind::Expected("a smaller number")))?;
+                Token::Number { value, wide: false }
+            }
+            '\'' => {
+                if chars.get(i + 2) != Some(&'\'') {
+                    return Err(error(AsmErrorKind::Expected("a character like 'a'")));
+                }
+                i += 3;
+                Token::Number {
+                    value: chars[i - 2] as i64,
+                    wide: false,
+                }
+            }
+            '"' => {
+                let text = take_while(&chars, i + 1, |c| c != '"');
+                i += 1 + text.chars().count();
+                if chars.get(i) != Some(&'"') {
+                    return Err(error(AsmErrorKind::Expected("closing '\"'")));
+                }
+                i += 1;
+                Token::Str(text)
+            }
+            '.' | '@' => {
+                let name = take_while(&chars, i + 1, is_identifier_char);
+                if name.is_empty() {
+                    return Err(error(AsmErrorKind::UnexpectedCharacter(c)));
+                }
+                i += 1 + name.len();
+                if c == '.' {
+                    Token::Directive(name.to_lowercase())
+                } else {
+                    Token::Local(name)
+                }
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let name = take_while(&chars, i, is_identifier_char);
+                i += name.len();
+                Token::Identifier(name)
+            }
+            '<' if chars.get(i + 1) == Some(&'<') => {
+                i += 2;
+                Token::ShiftLeft
+            }
+            '>' if chars.get(i + 1) == Some(&'>') => {
+                i += 2;
+                Token::ShiftRight
+            }
+            '#' | ',' | '(' | ')' | ':' | '=' | '+' | '-' | '*' | '/' | '&' | '|' | '^' | '<'
+            | '>' | '~' => {
+                i += 1;
+                Token::Punct(c)
+            }
+            _ => return Err(error(AsmErrorKind::UnexpectedCharacter(c))),
+        };
+
+        tokens.push(Spanned { token, column });
+    }
+
+    Ok(tokens)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn take_while<F>(chars: &[char], start: usize, predicate: F) -> String
+where
+    F: Fn(char) -> bool,
+{
+    chars
+        .iter()
+        .skip(start)
+        .take_while(|c| predicate(**c))
+        .collect()
+}
@@ -0,0 +1,470 @@
+// Turns the tokens of a line into a label and a statement. Expressions are kept as
+// trees, since their symbols may only be defined further down the source.
+
+use super::error::{AsmError, AsmErrorKind};
+use super::lexer::{Spanned, Token};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number { value: i64, wide: bool },
+    Symbol { name: String, column: usize },
+    Local { name: String, column: usize },
+    // `*`, the address of the current statement
+    Current,
+    Unary(char, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Expr {
+    // Whether the value was written in a way that asks for absolute addressing
+    pub fn is_wide(&self) -> bool {
+        match self {
+            Expr::Number { wide, .. } => *wide,
+            Expr::Unary(_, expr) => expr.is_wide(),
+            Expr::Binary(_, left, right) => left.is_wide() || right.is_wide(),
+            _ => false,
+        }
+    }
+}
+
+// The operand as written. The assembler maps it to an addressing mode once it knows
+// the instruction and, for zero page forms, the value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    Accumulator,
+    Immediate(Expr),
+    Direct(Expr),
+    IndexedX(Expr),
+    IndexedY(Expr),
+    Indirect(Expr),
+    IndirectX(Expr),
+    IndirectY(Expr),
+    // `zp,target` of BBR and BBS
+    Pair(Expr, Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataItem {
+    Expr(Expr),
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Instruction { mnemonic: String, operand: Operand },
+    Org(Expr),
+    Byte(Vec<DataItem>),
+    Word(Vec<Expr>),
+    // `name = value`
+    Assign { name: String, value: Expr },
+    // `.res` and `.fill`: a count of bytes, all set to the fill value or zero
+    Reserve { count: Expr, value: Option<Expr> },
+    Segment(String),
+    Include(String),
+    Incbin(String),
+    If(Expr),
+    Else,
+    Endif,
+    Macro { name: String, params: Vec<String> },
+    Endmacro,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Label {
+    Global(String),
+    Local(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub number: usize,
+    pub label: Option<(Label, usize)>,
+    // The statement and the column it starts at
+    pub statement: Option<(Statement, usize)>,
+}
+
+pub fn parse_line(tokens: &[Spanned], number: usize) -> Result<Line, AsmError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        line: number,
+    };
+    parser.line()
+}
+
+struct Parser<'a> {
+    tokens: &'a [Spanned],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&mut self) -> Result<Line, AsmError> {
+        let mut line = Line {
+            number: self.line,
+            label: None,
+            statement: None,
+        };
+
+        let first = self.tokens.first().map(|spanned| &spanned.token);
+        let second = self.tokens.get(1).map(|spanned| &spanned.token);
+        match (first, second) {
+            (Some(Token::Identifier(name)), Some(Token::Punct('='))) => {
+                let column = self.column();
+                self.position = 2;
+                let value = self.expr()?;
+                line.statement = Some((
+                    Statement::Assign {
+                        name: name.clone(),
+                        value,
+                    },
+                    column,
+                ));
+                self.end()?;
+                return Ok(line);
+            }
+            (Some(Token::Identifier(name)), Some(Token::Punct(':'))) => {
+                line.label = Some((Label::Global(name.clone()), self.column()));
+                self.position = 2;
+            }
+            (Some(Token::Local(name)), Some(Token::Punct(':'))) => {
+                line.label = Some((Label::Local(name.clone()), self.column()));
+                self.position = 2;
+            }
+            _ => {}
+        }
+
+        let column = self.column();
+        let statement = match self.next() {
+            None => return Ok(line),
+            Some(Token::Directive(name)) => self.directive(name, column)?,
+            Some(Token::Identifier(mnemonic)) => Statement::Instruction {
+                mnemonic: mnemonic.to_uppercase(),
+                operand: self.operand()?,
+            },
+            Some(token) => return Err(self.unexpected(token, column)),
+        };
+        line.statement = Some((statement, column));
+        self.end()?;
+
+        Ok(line)
+    }
+
+    fn directive(&mut self, name: String, column: usize) -> Result<Statement, AsmError> {
+        match name.as_str() {
+            "org" => Ok(Statement::Org(self.expr()?)),
+            "byte" => {
+                let mut items = vec![];
+                loop {
+                    match self.peek() {
+                        Some(Token::Str(text)) => {
+                            items.push(DataItem::Str(text.clone()));
+                            self.position += 1;
+                        }
+                        _ => items.push(DataItem::Expr(self.expr()?)),
+                    }
+                    if !self.eat(&Token::Punct(',')) {
+                        return Ok(Statement::Byte(items));
+                    }
+                }
+            }
+            "word" => {
+                let mut words = vec![self.expr()?];
+                while self.eat(&Token::Punct(',')) {
+                    words.push(self.expr()?);
+                }
+                Ok(Statement::Word(words))
+            }
+            "res" | "fill" => {
+                let count = self.expr()?;
+                let value = if self.eat(&Token::Punct(',')) {
+                    Some(self.expr()?)
+                } else {
+                    None
+                };
+                Ok(Statement::Reserve { count, value })
+            }
+            "segment" => Ok(Statement::Segment(self.string()?)),
+            "include" => Ok(Statement::Include(self.string()?)),
+            "incbin" => Ok(Statement::Incbin(self.string()?)),
+            "if" => Ok(Statement::If(self.expr()?)),
+            "else" => Ok(Statement::Else),
+            "endif" => Ok(Statement::Endif),
+            "macro" => {
+                let name = self.identifier()?;
+                let mut params = vec![];
+                if self.peek().is_some() {
+                    params.push(self.identifier()?);
+                    while self.eat(&Token::Punct(',')) {
+                        params.push(self.identifier()?);
+                    }
+                }
+                Ok(Statement::Macro { name, params })
+            }
+            "endmacro" => Ok(Statement::Endmacro),
+            _ => Err(AsmError::new(
+                self.line,
+                column,
+                AsmErrorKind::UnknownDirective(name),
+            )),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        match self.peek() {
+            None => return Ok(Operand::None),
+            Some(Token::Identifier(name))
+                if name.eq_ignore_ascii_case("A") && self.tokens.len() == self.position + 1 =>
+            {
+                self.position += 1;
+                return Ok(Operand::Accumulator);
+            }
+            Some(Token::Punct('#')) => {
+                self.position += 1;
+                return Ok(Operand::Immediate(self.expr()?));
+            }
+            Some(Token::Punct('(')) => {
+                if let Some(operand) = self.indirect()? {
+                    return Ok(operand);
+                }
+            }
+            _ => {}
+        }
+
+        let expr = self.expr()?;
+        if !self.eat(&Token::Punct(',')) {
+            return Ok(Operand::Direct(expr));
+        }
+        if self.eat_register("X") {
+            Ok(Operand::IndexedX(expr))
+        } else if self.eat_register("Y") {
+            Ok(Operand::IndexedY(expr))
+        } else {
+            Ok(Operand::Pair(expr, self.expr()?))
+        }
+    }
+
+    // `(expr)`, `(expr,X)` or `(expr),Y`. Anything else that starts with a parenthesis,
+    // like `(1+2)*3`, is an expression, in which case this backs off and returns None.
+    fn indirect(&mut self) -> Result<Option<Operand>, AsmError> {
+        let start = self.position;
+        self.position += 1;
+        let expr = self.expr()?;
+
+        let operand = if self.eat(&Token::Punct(',')) {
+            if self.eat_register("X") && self.eat(&Token::Punct(')')) {
+                Some(Operand::IndirectX(expr))
+            } else {
+                None
+            }
+        } else if self.eat(&Token::Punct(')')) {
+            if self.peek().is_none() {
+                Some(Operand::Indirect(expr))
+            } else if self.eat(&Token::Punct(',')) && self.eat_register("Y") {
+                Some(Operand::IndirectY(expr))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        if operand.is_none() || self.peek().is_some() {
+            self.position = start;
+            return Ok(None);
+        }
+        Ok(operand)
+    }
+
+    // Expressions, from the loosest binding operator to the tightest:
+    // `|`, `^`, `&`, `<<` `>>`, `+` `-`, `*` `/`, then unary `-` `~` `<` `>`
+    fn expr(&mut self) -> Result<Expr, AsmError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, AsmError> {
+        const LEVELS: [&[(Token, BinaryOp)]; 6] = [
+            &[(Token::Punct('|'), BinaryOp::Or)],
+            &[(Token::Punct('^'), BinaryOp::Xor)],
+            &[(Token::Punct('&'), BinaryOp::And)],
+            &[
+                (Token::ShiftLeft, BinaryOp::ShiftLeft),
+                (Token::ShiftRight, BinaryOp::ShiftRight),
+            ],
+            &[
+                (Token::Punct('+'), BinaryOp::Add),
+                (Token::Punct('-'), BinaryOp::Subtract),
+            ],
+            &[
+                (Token::Punct('*'), BinaryOp::Multiply),
+                (Token::Punct('/'), BinaryOp::Divide),
+            ],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for (token, op) in LEVELS[level] {
+                if self.eat(token) {
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, AsmError> {
+        for op in ['-', '~', '<', '>'] {
+            if self.eat(&Token::Punct(op)) {
+                return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, AsmError> {
+        let column = self.column();
+        match self.next() {
+            Some(Token::Number { value, wide }) => Ok(Expr::Number { value, wide }),
+            Some(Token::Identifier(name)) => Ok(Expr::Symbol { name, column }),
+            Some(Token::Local(name)) => Ok(Expr::Local { name, column }),
+            Some(Token::Punct('*')) => Ok(Expr::Current),
+            Some(Token::Punct('(')) => {
+                let expr = self.expr()?;
+                if !self.eat(&Token::Punct(')')) {
+                    return Err(self.expected("')'"));
+                }
+                Ok(expr)
+            }
+            Some(token) => Err(self.unexpected(token, column)),
+            None => Err(self.expected("an expression")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, AsmError> {
+        match self.peek() {
+            Some(Token::Str(text)) => {
+                let text = text.clone();
+                self.position += 1;
+                Ok(text)
+            }
+            _ => Err(self.expected("a string")),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, AsmError> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|spanned| &spanned.token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_register(&mut self, register: &str) -> bool {
+        match self.peek() {
+            Some(Token::Identifier(name)) if name.eq_ignore_ascii_case(register) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Column of the next token, or just past the last one at the end of the line
+    fn column(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some(spanned) => spanned.column,
+            None => self.tokens.last().map_or(1, |spanned| spanned.column + 1),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), AsmError> {
+        let column = self.column();
+        match self.next() {
+            None => Ok(()),
+            Some(token) => Err(self.unexpected(token, column)),
+        }
+    }
+
+    fn unexpected(&self, token: Token, column: usize) -> AsmError {
+        AsmError::new(
+            self.line,
+            column,
+            AsmErrorKind::UnexpectedToken(token.describe()),
+        )
+    }
+
+    fn expected(&self, expected: &'static str) -> AsmError {
+        let column = self.column();
+        AsmError::new(self.line, column, AsmErrorKind::Expected(expected))
+    }
+}
+
+// The tokens after a leading `label:`, if there is one
+pub fn skip_label(tokens: &[Spanned]) -> &[Spanned] {
+    match (tokens.first(), tokens.get(1)) {
+        (
+            Some(Spanned {
+                token: Token::Identifier(_) | Token::Local(_),
+                ..
+            }),
+            Some(Spanned {
+                token: Token::Punct(':'),
+                ..
+            }),
+        ) => &tokens[2..],
+        _ => tokens,
+    }
+}
+
+// The directive a line starts with, after any label
+pub fn directive(tokens: &[Spanned]) -> Option<&str> {
+    match skip_label(tokens).first() {
+        Some(Spanned {
+            token: Token::Directive(name),
+            ..
+        }) => Some(name),
+        _ => None,
+    }
+}
@@ -1,42 +1,140 @@
-mod instruction_set;
+pub mod bus;
+pub mod error;
+pub mod instruction_set;
+pub mod micro;
 mod status_flag;
+pub mod step;
+pub mod variant;
 use crate::util;
+use bus::{Bus, Ram};
+use error::{CpuError, IllegalOpcodePolicy};
 use instruction_set::instruction::addressing_mode::AddressingMode;
-use instruction_set::INSTRUCTION_MAP;
+use instruction_set::instruction::Instruction;
+use micro::MicroState;
 use status_flag::StatusFlag;
+use step::Step;
+use variant::Variant;
 
-pub struct CPU {
+// The stack lives in page one and grows downwards
+const STACK: u16 = 0x0100;
+const STACK_RESET: u8 = 0xFD;
+
+// Interrupts are disabled and the unused bit reads as one at power-on
+const STATUS_RESET: u8 = StatusFlag::InterruptDisable as u8 | StatusFlag::Unused as u8;
+
+const NMI_VECTOR: u16 = 0xFFFA;
+// BRK shares its vector with IRQ
+const IRQ_BRK_VECTOR: u16 = 0xFFFE;
+
+pub struct CPU<B: Bus = Ram> {
     pub register_a: u8,
     pub register_x: u8,
     pub register_y: u8,
     pub status: u8,
+    pub stack_pointer: u8,
     pub program_counter: u16,
-    memory: [u8; 0xFFFF],
+    pub cycles: u64,
+    // When set, BRK stops `run` instead of taking the interrupt. Test programs rely on
+    // this to terminate.
+    pub halt_on_brk: bool,
+    // When set, a jump or branch to itself also stops `run`, unless an interrupt is
+    // about to break out of the loop, and so does WAI with nothing to wake it. Test
+    // programs use such a trap to signal that they are done, but it is also how real
+    // programs idle until a device interrupts them.
+    pub stop_on_self_jump: bool,
+    pub illegal_opcode_policy: IllegalOpcodePolicy,
+    // ANE and LXA OR the accumulator with a constant that varies between chips, and
+    // even with temperature. 0xEE is what most NMOS parts show; 0xFF and 0xEF also
+    // occur.
+    pub unstable_magic: u8,
+    pub variant: Variant,
+    jammed: bool,
+    // Set by STP until the next reset
+    stopped: bool,
+    // Set by WAI until an interrupt line is asserted
+    waiting: bool,
+    irq_line: bool,
+    nmi_line: bool,
+    nmi_pending: bool,
+    // Set when indexing carries into the high byte of the effective address
+    page_crossed: bool,
+    // Recorded for `step`
+    effective_address: Option<u16>,
+    branch_taken: bool,
+    // Set when the instruction being executed writes the program counter, which is then
+    // not advanced past the operand
+    jumped: bool,
+    // Effective address and operand already fetched by the cycle-accurate core. While
+    // set, instructions take their operand from here instead of the bus.
+    operand_latch: Option<(u16, u8)>,
+    micro: MicroState,
+    pub bus: B,
+}
+
+impl Default for CPU<Ram> {
+    fn default() -> Self {
+        Self::new(Variant::default())
+    }
+}
+
+impl CPU<Ram> {
+    pub fn new(variant: Variant) -> Self {
+        Self::with_bus(Ram::new(), variant)
+    }
 }
 
-impl CPU {
-    pub fn new() -> Self {
+impl<B: Bus> CPU<B> {
+    pub fn with_bus(bus: B, variant: Variant) -> Self {
         CPU {
             register_a: 0,
             register_x: 0,
             register_y: 0,
-            status: 0,
+            status: STATUS_RESET,
+            stack_pointer: STACK_RESET,
             program_counter: 0,
-            memory: [0; 0xFFFF],
+            cycles: 0,
+            halt_on_brk: true,
+            stop_on_self_jump: false,
+            illegal_opcode_policy: IllegalOpcodePolicy::default(),
+            unstable_magic: 0xEE,
+            variant,
+            jammed: false,
+            stopped: false,
+            waiting: false,
+            irq_line: false,
+            nmi_line: false,
+            nmi_pending: false,
+            page_crossed: false,
+            effective_address: None,
+            branch_taken: false,
+            jumped: false,
+            operand_latch: None,
+            micro: MicroState::default(),
+            bus,
         }
     }
 
-    fn mem_read(&self, addr: u16) -> u8 {
-        self.memory[addr as usize]
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
-        self.memory[addr as usize] = data;
+        self.bus.write(addr, data);
     }
 
+    // 16-bit accesses wrap around the top of the address space, so a word at $FFFF
+    // takes its high byte from $0000
     fn mem_read_u16(&mut self, pos: u16) -> u16 {
         let lo = self.mem_read(pos);
-        let hi = self.mem_read(pos + 1);
+        let hi = self.mem_read(pos.wrapping_add(1));
+        u16::from_le_bytes([lo, hi])
+    }
+
+    // Pointers in the zero page wrap within it, so a pointer at $FF takes its high byte
+    // from $00 rather than $0100
+    fn mem_read_u16_zero_page(&mut self, pos: u8) -> u16 {
+        let lo = self.mem_read(pos as u16);
+        let hi = self.mem_read(pos.wrapping_add(1) as u16);
         u16::from_le_bytes([lo, hi])
     }
 
@@ -44,29 +142,138 @@ impl CPU {
         let hi = (data >> 8) as u8;
         let lo = (data & 0xff) as u8;
         self.mem_write(pos, lo);
-        self.mem_write(pos + 1, hi);
+        self.mem_write(pos.wrapping_add(1), hi);
     }
 
     pub fn reset(&mut self) {
         self.register_a = 0;
         self.register_x = 0;
         self.register_y = 0;
-        self.status = 0;
+        self.status = STATUS_RESET;
+        self.stack_pointer = STACK_RESET;
+        self.nmi_pending = false;
+        self.jammed = false;
+        self.stopped = false;
+        self.waiting = false;
+        self.micro = MicroState::default();
 
         self.program_counter = self.mem_read_u16(0xFFFC);
+
+        // The reset sequence takes as long as an interrupt
+        self.cycles += 7;
     }
 
     pub fn load(&mut self, program: Vec<u8>) {
-        self.memory[0x8000..(0x8000 + program.len())].copy_from_slice(&program[..]);
+        for (i, byte) in program.iter().enumerate() {
+            self.mem_write(0x8000u16.wrapping_add(i as u16), *byte);
+        }
         self.mem_write_u16(0xFFFC, 0x8000);
     }
 
-    pub fn load_and_run(&mut self, program: Vec<u8>) {
+    // IRQ is level-triggered: it is serviced for as long as the line is held and the
+    // I flag is clear, so the device must release it once acknowledged.
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    // NMI is edge-triggered: only a transition to asserted latches a request, and
+    // holding the line does not retrigger it.
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.nmi_pending || (self.irq_line && self.status & StatusFlag::InterruptDisable as u8 == 0)
+    }
+
+    // Interrupts are only recognised between instructions. NMI wins over IRQ.
+    fn poll_interrupts(&mut self) {
+        if self.jammed || self.stopped {
+            return;
+        }
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(NMI_VECTOR, false);
+            self.cycles += 7;
+        } else if self.interrupt_pending() {
+            self.interrupt(IRQ_BRK_VECTOR, false);
+            self.cycles += 7;
+        }
+    }
+
+    pub fn load_and_run(&mut self, program: Vec<u8>) -> Result<(), CpuError> {
         self.load(program);
         self.reset();
         self.run()
     }
 
+    fn stack_push(&mut self, data: u8) {
+        self.mem_write(STACK + self.stack_pointer as u16, data);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+    }
+
+    fn stack_pop(&mut self) -> u8 {
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        self.mem_read(STACK + self.stack_pointer as u16)
+    }
+
+    fn stack_push_u16(&mut self, data: u16) {
+        let [lo, hi] = data.to_le_bytes();
+        self.stack_push(hi);
+        self.stack_push(lo);
+    }
+
+    fn stack_pop_u16(&mut self) -> u16 {
+        let lo = self.stack_pop();
+        let hi = self.stack_pop();
+        u16::from_le_bytes([lo, hi])
+    }
+
+    // Pushes the return address and status, then jumps through the given vector.
+    // The B flag only distinguishes BRK from a hardware interrupt on the stack copy.
+    fn interrupt(&mut self, vector: u16, break_flag: bool) {
+        self.stack_push_u16(self.program_counter);
+        self.stack_push(self.status_for_push(break_flag));
+
+        self.mask_interrupts();
+        let address = self.mem_read_u16(vector);
+        self.jump(address);
+    }
+
+    fn jump(&mut self, address: u16) {
+        self.program_counter = address;
+        self.jumped = true;
+    }
+
+    // The 65C02 also leaves decimal mode when it takes an interrupt
+    fn mask_interrupts(&mut self) {
+        self.set_status_bit(StatusFlag::InterruptDisable);
+        if self.variant.is_cmos() {
+            self.unset_status_bit(StatusFlag::Decimal);
+        }
+        self.waiting = false;
+    }
+
+    // The B and unused bits only exist on the stack copy of the status register
+    fn status_for_push(&self, break_flag: bool) -> u8 {
+        let status = self.status | StatusFlag::Unused as u8;
+        if break_flag {
+            status | StatusFlag::Break as u8
+        } else {
+            status
+        }
+    }
+
+    fn restore_status(&mut self, value: u8) {
+        self.status = value;
+        self.unset_status_bit(StatusFlag::Break);
+        self.set_status_bit(StatusFlag::Unused);
+    }
+
     fn set_status_bit(&mut self, flag: StatusFlag) {
         self.status |= flag as u8;
     }
@@ -94,7 +301,11 @@ impl CPU {
     }
 
     fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
-        match mode {
+        if let Some((address, _)) = self.operand_latch {
+            return address;
+        }
+
+        let address = match mode {
             AddressingMode::Immediate | AddressingMode::Relative => self.program_counter,
 
             AddressingMode::ZeroPage => self.mem_read(self.program_counter) as u16,
@@ -103,56 +314,87 @@ impl CPU {
 
             AddressingMode::ZeroPageX => {
                 let pos = self.mem_read(self.program_counter);
-                let addr = pos.wrapping_add(self.register_x) as u16;
-                addr
+                pos.wrapping_add(self.register_x) as u16
             }
 
             AddressingMode::ZeroPageY => {
                 let pos = self.mem_read(self.program_counter);
-                let addr = pos.wrapping_add(self.register_y) as u16;
-                addr
+                pos.wrapping_add(self.register_y) as u16
             }
 
             AddressingMode::AbsoluteX => {
                 let base = self.mem_read_u16(self.program_counter);
                 let addr = base.wrapping_add(self.register_x as u16);
+                self.page_crossed = util::is_page_crossed(base, addr);
                 addr
             }
 
             AddressingMode::AbsoluteY => {
                 let base = self.mem_read_u16(self.program_counter);
                 let addr = base.wrapping_add(self.register_y as u16);
+                self.page_crossed = util::is_page_crossed(base, addr);
                 addr
             }
 
+            AddressingMode::Indirect => {
+                let ptr = self.mem_read_u16(self.program_counter);
+
+                // NMOS bug: a vector at $xxFF takes its high byte from $xx00 instead of
+                // crossing into the next page
+                if self.variant.is_cmos() {
+                    self.mem_read_u16(ptr)
+                } else {
+                    let lo = self.mem_read(ptr);
+                    let hi = self.mem_read((ptr & 0xFF00) | (ptr as u8).wrapping_add(1) as u16);
+                    u16::from_le_bytes([lo, hi])
+                }
+            }
+
             AddressingMode::IndirectX => {
                 let base = self.mem_read(self.program_counter);
 
-                let ptr: u8 = (base as u8).wrapping_add(self.register_x);
-                let lo = self.mem_read(ptr as u16);
-                let hi = self.mem_read(ptr.wrapping_add(1) as u16);
-                (hi as u16) << 8 | (lo as u16)
+                let ptr: u8 = base.wrapping_add(self.register_x);
+                self.mem_read_u16_zero_page(ptr)
             }
 
             AddressingMode::IndirectY => {
                 let base = self.mem_read(self.program_counter);
 
-                let lo = self.mem_read(base as u16);
-                let hi = self.mem_read((base as u8).wrapping_add(1) as u16);
-                let deref_base = (hi as u16) << 8 | (lo as u16);
+                let deref_base = self.mem_read_u16_zero_page(base);
                 let deref = deref_base.wrapping_add(self.register_y as u16);
+                self.page_crossed = util::is_page_crossed(deref_base, deref);
                 deref
             }
 
+            AddressingMode::ZeroPageIndirect => {
+                let base = self.mem_read(self.program_counter);
+                self.mem_read_u16_zero_page(base)
+            }
+
+            AddressingMode::AbsoluteIndexedIndirect => {
+                let base = self.mem_read_u16(self.program_counter);
+                self.mem_read_u16(base.wrapping_add(self.register_x as u16))
+            }
+
+            // The zero page address tested by BBR and BBS. The branch offset follows it.
+            AddressingMode::ZeroPageRelative => self.mem_read(self.program_counter) as u16,
+
+            // The instruction set never pairs these modes with a memory operand
             AddressingMode::Implicit | AddressingMode::Accumulator => {
-                panic!("mode {:?} is not supported", mode);
+                unreachable!("mode {:?} has no operand address", mode);
             }
+        };
+
+        if !matches!(mode, AddressingMode::Immediate | AddressingMode::Relative) {
+            self.effective_address = Some(address);
         }
+        address
     }
 
     fn get_operand_value(&mut self, mode: &AddressingMode) -> u8 {
-        match mode {
-            AddressingMode::Accumulator => self.register_a,
+        match (mode, self.operand_latch) {
+            (AddressingMode::Accumulator, _) => self.register_a,
+            (_, Some((_, value))) => value,
             _ => {
                 let addr = self.get_operand_address(mode);
                 self.mem_read(addr)
@@ -160,37 +402,205 @@ impl CPU {
         }
     }
 
+    // Write-back for stores and read-modify-write instructions such as the shifts
+    fn set_operand_value(&mut self, mode: &AddressingMode, value: u8) {
+        match (mode, &mut self.operand_latch) {
+            (AddressingMode::Accumulator, _) => {
+                self.register_a = value;
+            }
+            (_, Some((_, latched))) => {
+                *latched = value;
+            }
+            _ => {
+                let address = self.get_operand_address(mode);
+                self.mem_write(address, value)
+            }
+        }
+    }
+
+    // The 65C02 lets shifts and rotates skip the fix-up cycle like reads do. INC and DEC
+    // still always spend it.
+    fn get_shift_operand_value(&mut self, mode: &AddressingMode) -> u8 {
+        if self.variant.is_cmos() {
+            self.get_read_operand_value(mode)
+        } else {
+            self.get_operand_value(mode)
+        }
+    }
+
+    // Indexed reads skip the fix-up cycle unless the index carries into the high byte.
+    // Stores and read-modify-write instructions always spend it, so it is already part
+    // of their base cycle count.
+    fn get_read_operand_value(&mut self, mode: &AddressingMode) -> u8 {
+        let value = self.get_operand_value(mode);
+        if self.page_crossed {
+            self.cycles += 1;
+        }
+        value
+    }
+
+    // A taken branch costs one extra cycle, and another if it lands in a different page
     fn branch(&mut self, mode: &AddressingMode) {
         let operand = self.get_operand_value(mode);
-        self.program_counter += 1;
-        self.program_counter = util::get_address_from_offset(self.program_counter, operand);
+        self.program_counter = self.program_counter.wrapping_add(1);
+
+        let target = util::get_address_from_offset(self.program_counter, operand);
+        self.branch_taken = true;
+        self.cycles += 1;
+        if util::is_page_crossed(self.program_counter, target) {
+            self.cycles += 1;
+        }
+        self.jump(target);
     }
 
-    fn and(&mut self, mode: &AddressingMode) {
-        let operand = self.get_operand_value(mode);
+    fn add_to_register_a(&mut self, operand: u8) {
+        let carry = self.is_flag_set(StatusFlag::Carry) as u16;
+        let sum = self.register_a as u16 + operand as u16 + carry;
+        let result = sum as u8;
 
-        self.register_a = self.register_a & operand;
+        // Signed overflow: both inputs share a sign that differs from the result's sign
+        let overflow = (self.register_a ^ result) & (operand ^ result) & 0b1000_0000 != 0;
+
+        self.update_flag(StatusFlag::Carry, sum > 0xFF);
+        self.update_flag(StatusFlag::Overflow, overflow);
+
+        self.register_a = result;
         self.update_zero_and_negative_flags(self.register_a);
     }
 
-    fn asl(&mut self, mode: &AddressingMode) {
-        let operand = self.get_operand_value(mode);
-        let result = operand << 1;
+    // Decimal mode follows the NMOS behaviour documented by Bruce Clark:
+    // http://www.6502.org/tutorials/decimal_mode.html
+    // Zero comes from the binary sum, while Negative and Overflow come from the
+    // intermediate result before the high nibble is adjusted.
+    fn add_to_register_a_decimal(&mut self, operand: u8) {
+        let a = self.register_a;
+        let carry = self.is_flag_set(StatusFlag::Carry) as u8;
+        let binary = a.wrapping_add(operand).wrapping_add(carry);
 
-        self.update_flag(StatusFlag::Carry, util::get_bit_at(operand, 7));
-        self.update_zero_and_negative_flags(result);
+        let mut lo = (a & 0x0F) as u16 + (operand & 0x0F) as u16 + carry as u16;
+        if lo >= 0x0A {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+        let mut sum = (a & 0xF0) as u16 + (operand & 0xF0) as u16 + lo;
+        let signed_sum = (a & 0xF0) as i8 as i16 + (operand & 0xF0) as i8 as i16 + lo as i16;
+
+        self.update_flag(StatusFlag::Zero, binary == 0);
+        self.update_flag(StatusFlag::Negative, sum & 0b1000_0000 != 0);
+        self.update_flag(StatusFlag::Overflow, !(-128..=127).contains(&signed_sum));
+
+        if sum >= 0xA0 {
+            sum += 0x60;
+        }
+
+        self.update_flag(StatusFlag::Carry, sum > 0xFF);
+        self.register_a = sum as u8;
+    }
+
+    // All flags are set exactly as in binary mode; only the accumulator differs.
+    fn subtract_from_register_a_decimal(&mut self, operand: u8) {
+        let a = self.register_a;
+        let borrow = !self.is_flag_set(StatusFlag::Carry) as i16;
+
+        self.add_to_register_a(!operand);
+
+        let mut lo = (a & 0x0F) as i16 - (operand & 0x0F) as i16 - borrow;
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+        let mut result = (a & 0xF0) as i16 - (operand & 0xF0) as i16 + lo;
+        if result < 0 {
+            result -= 0x60;
+        }
+
+        self.register_a = result as u8;
+    }
 
-        match mode {
-            AddressingMode::Accumulator => {
-                self.register_a = result;
+    // The 2A03 has the D flag but no decimal arithmetic
+    fn decimal_mode(&mut self) -> bool {
+        self.variant.has_decimal_mode() && self.is_flag_set(StatusFlag::Decimal)
+    }
+
+    // The 65C02 spends an extra cycle on decimal ADC and SBC, which it uses to set
+    // Negative and Zero from the corrected result
+    fn decimal_penalty(&mut self, mnemonic: &str) -> bool {
+        self.variant.is_cmos() && matches!(mnemonic, "ADC" | "SBC") && self.decimal_mode()
+    }
+
+    fn add_with_carry(&mut self, operand: u8) {
+        if self.decimal_mode() {
+            self.add_to_register_a_decimal(operand);
+            if self.variant.is_cmos() {
+                self.update_zero_and_negative_flags(self.register_a);
             }
-            _ => {
-                let address = self.get_operand_address(mode);
-                self.mem_write(address, result)
+        } else {
+            self.add_to_register_a(operand);
+        }
+    }
+
+    fn subtract_with_carry(&mut self, operand: u8) {
+        if self.decimal_mode() {
+            self.subtract_from_register_a_decimal(operand);
+            if self.variant.is_cmos() {
+                self.update_zero_and_negative_flags(self.register_a);
             }
+        } else {
+            // A - M - (1 - C) is the same as A + !M + C. Carry acts as an inverted borrow.
+            self.add_to_register_a(!operand);
         }
     }
 
+    fn shift_left(&mut self, operand: u8) -> u8 {
+        let result = operand << 1;
+
+        self.update_flag(StatusFlag::Carry, util::get_bit_at(operand, 7));
+        self.update_zero_and_negative_flags(result);
+        result
+    }
+
+    fn shift_right(&mut self, operand: u8) -> u8 {
+        let result = operand >> 1;
+
+        self.update_flag(StatusFlag::Carry, util::get_bit_at(operand, 0));
+        self.update_zero_and_negative_flags(result);
+        result
+    }
+
+    fn rotate_left(&mut self, operand: u8) -> u8 {
+        let carry = self.is_flag_set(StatusFlag::Carry) as u8;
+        let result = operand << 1 | carry;
+
+        self.update_flag(StatusFlag::Carry, util::get_bit_at(operand, 7));
+        self.update_zero_and_negative_flags(result);
+        result
+    }
+
+    fn rotate_right(&mut self, operand: u8) -> u8 {
+        let carry = self.is_flag_set(StatusFlag::Carry) as u8;
+        let result = operand >> 1 | carry << 7;
+
+        self.update_flag(StatusFlag::Carry, util::get_bit_at(operand, 0));
+        self.update_zero_and_negative_flags(result);
+        result
+    }
+
+    fn adc(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+        self.add_with_carry(operand);
+    }
+
+    fn and(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+
+        self.register_a &= operand;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn asl(&mut self, mode: &AddressingMode) {
+        let operand = self.get_shift_operand_value(mode);
+        let result = self.shift_left(operand);
+        self.set_operand_value(mode, result);
+    }
+
     fn bcc(&mut self) {
         if !self.is_flag_set(StatusFlag::Carry) {
             self.branch(&AddressingMode::Relative);
@@ -210,12 +620,14 @@ impl CPU {
     }
 
     fn bit(&mut self, mode: &AddressingMode) {
-        let operand = self.get_operand_value(mode);
+        let operand = self.get_read_operand_value(mode);
         let result = self.register_a & operand;
 
         self.update_flag(StatusFlag::Zero, result == 0);
-        self.update_flag(StatusFlag::Overflow, util::get_bit_at(operand, 6));
-        self.update_flag(StatusFlag::Negative, util::get_bit_at(operand, 7));
+        if *mode != AddressingMode::Immediate {
+            self.update_flag(StatusFlag::Overflow, util::get_bit_at(operand, 6));
+            self.update_flag(StatusFlag::Negative, util::get_bit_at(operand, 7));
+        }
     }
 
     fn bmi(&mut self) {
@@ -248,473 +660,4337 @@ impl CPU {
         }
     }
 
-    fn inx(&mut self) {
-        self.register_x = self.register_x.wrapping_add(1);
-        self.update_zero_and_negative_flags(self.register_x);
+    fn clc(&mut self) {
+        self.unset_status_bit(StatusFlag::Carry);
     }
 
-    fn lda(&mut self, mode: &AddressingMode) {
-        self.register_a = self.get_operand_value(mode);
-        self.update_zero_and_negative_flags(self.register_a);
+    fn cld(&mut self) {
+        self.unset_status_bit(StatusFlag::Decimal);
     }
 
-    fn ldx(&mut self, mode: &AddressingMode) {
-        self.register_x = self.get_operand_value(mode);
-        self.update_zero_and_negative_flags(self.register_x);
+    fn cli(&mut self) {
+        self.unset_status_bit(StatusFlag::InterruptDisable);
     }
 
-    fn ldy(&mut self, mode: &AddressingMode) {
-        self.register_y = self.get_operand_value(mode);
-        self.update_zero_and_negative_flags(self.register_y);
+    fn clv(&mut self) {
+        self.unset_status_bit(StatusFlag::Overflow);
     }
 
-    fn tax(&mut self) {
-        self.register_x = self.register_a;
-        self.update_zero_and_negative_flags(self.register_x);
+    fn compare(&mut self, mode: &AddressingMode, register: u8) {
+        let operand = self.get_read_operand_value(mode);
+        self.compare_value(register, operand);
     }
 
-    fn sta(&mut self, mode: &AddressingMode) {
-        let address = self.get_operand_address(mode);
-        self.mem_write(address, self.register_a);
+    fn compare_value(&mut self, register: u8, operand: u8) {
+        self.update_flag(StatusFlag::Carry, register >= operand);
+        self.update_zero_and_negative_flags(register.wrapping_sub(operand));
     }
 
-    pub fn run(&mut self) {
-        loop {
-            // Fetch
-            let opcode = self.mem_read(self.program_counter);
-            self.program_counter += 1;
+    fn cmp(&mut self, mode: &AddressingMode) {
+        self.compare(mode, self.register_a);
+    }
 
-            // Used to check if an instruction changes the program counter. See end of loop.
-            let program_counter_state = self.program_counter;
+    fn cpx(&mut self, mode: &AddressingMode) {
+        self.compare(mode, self.register_x);
+    }
 
-            // Decode
-            let instruction = INSTRUCTION_MAP
-                .get(&opcode)
-                .expect(&format!("OpCode {:x} is not recognized", opcode));
+    fn cpy(&mut self, mode: &AddressingMode) {
+        self.compare(mode, self.register_y);
+    }
 
-            // Execute
-            match opcode {
-                // AND
-                0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31 => {
-                    self.and(&instruction.mode)
-                }
+    fn dec(&mut self, mode: &AddressingMode) {
+        let result = self.get_operand_value(mode).wrapping_sub(1);
 
-                // ASL
-                0x0A | 0x06 | 0x16 | 0x0E | 0x1E => self.asl(&instruction.mode),
+        self.update_zero_and_negative_flags(result);
+        self.set_operand_value(mode, result);
+    }
 
-                // BCC
-                0x90 => self.bcc(),
+    fn dex(&mut self) {
+        self.register_x = self.register_x.wrapping_sub(1);
+        self.update_zero_and_negative_flags(self.register_x);
+    }
 
-                // BCS
-                0xB0 => self.bcs(),
+    fn dey(&mut self) {
+        self.register_y = self.register_y.wrapping_sub(1);
+        self.update_zero_and_negative_flags(self.register_y);
+    }
 
-                // BEQ
-                0xF0 => self.beq(),
+    fn eor(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
 
-                // BIT
-                0x24 | 0x2C => self.bit(&instruction.mode),
+        self.register_a ^= operand;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
 
-                // BMI
-                0x30 => self.bmi(),
+    fn inc(&mut self, mode: &AddressingMode) {
+        let result = self.get_operand_value(mode).wrapping_add(1);
 
-                // BNE
-                0xD0 => self.bne(),
+        self.update_zero_and_negative_flags(result);
+        self.set_operand_value(mode, result);
+    }
 
-                // BPL
-                0x10 => self.bpl(),
+    fn inx(&mut self) {
+        self.register_x = self.register_x.wrapping_add(1);
+        self.update_zero_and_negative_flags(self.register_x);
+    }
 
-                // BVC
-                0x50 => self.bvc(),
+    fn iny(&mut self) {
+        self.register_y = self.register_y.wrapping_add(1);
+        self.update_zero_and_negative_flags(self.register_y);
+    }
 
-                // BVS
-                0x70 => self.bvs(),
+    fn jmp(&mut self, mode: &AddressingMode) {
+        let target = self.get_operand_address(mode);
+        self.jump(target);
+    }
 
-                // LDA
-                0xA9 => self.lda(&instruction.mode),
+    // The return address pushed is that of the last byte of the JSR instruction
+    fn jsr(&mut self, mode: &AddressingMode) {
+        let target = self.get_operand_address(mode);
+        self.stack_push_u16(self.program_counter.wrapping_add(1));
+        self.jump(target);
+    }
 
-                // LDX
-                0xA2 => self.ldx(&instruction.mode),
+    // BRK is followed by a padding byte, so the pushed return address skips it
+    fn brk(&mut self) {
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.interrupt(IRQ_BRK_VECTOR, true);
+    }
 
-                // LDY
-                0xA0 => self.ldy(&instruction.mode),
+    fn rti(&mut self) {
+        self.plp();
+        let address = self.stack_pop_u16();
+        self.jump(address);
+    }
 
-                // STA
-                0x85 => self.sta(&instruction.mode),
+    fn rts(&mut self) {
+        let address = self.stack_pop_u16().wrapping_add(1);
+        self.jump(address);
+    }
 
-                // Implicit addressing opcodes
-                0xAA => self.tax(),
-                0xE8 => self.inx(),
-                0x00 => return,
+    fn lda(&mut self, mode: &AddressingMode) {
+        self.register_a = self.get_read_operand_value(mode);
+        self.update_zero_and_negative_flags(self.register_a);
+    }
 
-                _ => todo!(),
-            }
+    fn ldx(&mut self, mode: &AddressingMode) {
+        self.register_x = self.get_read_operand_value(mode);
+        self.update_zero_and_negative_flags(self.register_x);
+    }
 
-            // Some instructions modify the program counter. Do NOT increment the program
-            // counter after executing those instructions.
-            if self.program_counter == program_counter_state {
-                self.program_counter += (instruction.length - 1) as u16;
-            }
-        }
+    fn ldy(&mut self, mode: &AddressingMode) {
+        self.register_y = self.get_read_operand_value(mode);
+        self.update_zero_and_negative_flags(self.register_y);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    fn lsr(&mut self, mode: &AddressingMode) {
+        let operand = self.get_shift_operand_value(mode);
+        let result = self.shift_right(operand);
+        self.set_operand_value(mode, result);
+    }
 
-    #[test]
-    fn test_0xa9_lda_immediate_load_data() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0x05, 0x00]);
-        assert_eq!(cpu.register_a, 0x05);
-        assert!(cpu.status & 0b0000_0010 == 0b00);
-        assert!(cpu.status & 0b1000_0000 == 0);
+    fn ora(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+
+        self.register_a |= operand;
+        self.update_zero_and_negative_flags(self.register_a);
     }
 
-    #[test]
-    fn test_0xa9_lda_zero_flag() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0x00, 0x00]);
-        assert!(cpu.status & 0b0000_0010 == 0b10);
+    fn pha(&mut self) {
+        self.stack_push(self.register_a);
     }
 
-    #[test]
-    fn test_0xaa_tax_move_a_to_x() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0x10, 0xaa, 0x00]);
-        assert_eq!(cpu.register_x, 0x10);
+    fn php(&mut self) {
+        self.stack_push(self.status_for_push(true));
     }
 
-    #[test]
-    fn test_0xe8_inx_increments_base() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xe8, 0x00]);
+    fn pla(&mut self) {
+        self.register_a = self.stack_pop();
+        self.update_zero_and_negative_flags(self.register_a);
+    }
 
-        assert_eq!(cpu.register_x, 1)
+    fn plp(&mut self) {
+        let value = self.stack_pop();
+        self.restore_status(value);
     }
 
-    #[test]
-    fn test_0xe8_inx_increments_wraps() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xA9, 0xFF, 0xAA, 0xE8, 0x00]);
-        assert_eq!(cpu.register_x, 0b0000_0000);
-        assert!(cpu.status & (StatusFlag::Zero as u8) == StatusFlag::Zero as u8);
+    fn rol(&mut self, mode: &AddressingMode) {
+        let operand = self.get_shift_operand_value(mode);
+        let result = self.rotate_left(operand);
+        self.set_operand_value(mode, result);
     }
 
-    #[test]
-    fn test_0xe8_inx_set_negative_flag() {
-        let mut cpu = CPU::new();
-        cpu.register_x = 0b0111_1111;
-        cpu.load_and_run(vec![0xA9, 0b0111_1111, 0xAA, 0xE8, 0xe8, 0x00]);
-        assert!(cpu.status & (StatusFlag::Negative as u8) == StatusFlag::Negative as u8);
+    fn ror(&mut self, mode: &AddressingMode) {
+        let operand = self.get_shift_operand_value(mode);
+        let result = self.rotate_right(operand);
+        self.set_operand_value(mode, result);
     }
 
-    #[test]
-    fn test_0xe8_inx_no_negative_flag() {
-        let mut cpu = CPU::new();
-        cpu.register_x = 0b0111_1110;
-        cpu.load_and_run(vec![0xe8, 0x00]);
-        assert!(cpu.status & (StatusFlag::Negative as u8) == 0);
+    fn sbc(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+        self.subtract_with_carry(operand);
     }
 
-    #[test]
-    fn test_5_ops_working_together() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
-        assert_eq!(cpu.register_x, 0xc1)
+    fn sec(&mut self) {
+        self.set_status_bit(StatusFlag::Carry);
     }
 
-    #[test]
-    fn test_inx_overflow() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xff, 0xaa, 0xe8, 0xe8, 0x00]);
-        assert_eq!(cpu.register_x, 1)
+    fn sed(&mut self) {
+        self.set_status_bit(StatusFlag::Decimal);
     }
 
-    #[test]
-    #[should_panic]
-    fn test_unknown_opcode() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0x02, 0x00]);
+    fn sei(&mut self) {
+        self.set_status_bit(StatusFlag::InterruptDisable);
     }
 
-    // AND + Addressing Modes
+    fn tax(&mut self) {
+        self.register_x = self.register_a;
+        self.update_zero_and_negative_flags(self.register_x);
+    }
 
-    // Immediate
-    #[test]
-    fn test_0x29_and() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0b1010_1101, 0x29, 0b1111_1110, 0x00]);
-        assert_eq!(cpu.register_a, 0b1010_1100);
-        assert_eq!(
-            cpu.status & (StatusFlag::Negative as u8),
-            StatusFlag::Negative as u8
-        );
-        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+    fn tay(&mut self) {
+        self.register_y = self.register_a;
+        self.update_zero_and_negative_flags(self.register_y);
+    }
+
+    fn tsx(&mut self) {
+        self.register_x = self.stack_pointer;
+        self.update_zero_and_negative_flags(self.register_x);
+    }
+
+    fn txa(&mut self) {
+        self.register_a = self.register_x;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // Unlike the other transfers, TXS leaves the flags alone
+    fn txs(&mut self) {
+        self.stack_pointer = self.register_x;
+    }
+
+    fn tya(&mut self) {
+        self.register_a = self.register_y;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn sta(&mut self, mode: &AddressingMode) {
+        self.set_operand_value(mode, self.register_a);
+    }
+
+    fn stx(&mut self, mode: &AddressingMode) {
+        self.set_operand_value(mode, self.register_x);
+    }
+
+    fn sty(&mut self, mode: &AddressingMode) {
+        self.set_operand_value(mode, self.register_y);
+    }
+
+    // Undocumented instructions
+    // Reference: https://www.masswerk.at/nowgobang/2021/6502-illegal-opcodes
+
+    fn alr(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+        self.register_a = self.shift_right(self.register_a & operand);
+    }
+
+    fn anc(&mut self, mode: &AddressingMode) {
+        self.and(mode);
+
+        let negative = self.is_flag_set(StatusFlag::Negative);
+        self.update_flag(StatusFlag::Carry, negative);
+    }
+
+    fn ane(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+
+        self.register_a = (self.register_a | self.unstable_magic) & self.register_x & operand;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // Carry and Overflow come from bits 6 and 5 of the result rather than from the
+    // rotate. In decimal mode the result is BCD-corrected nibble by nibble, with the
+    // high nibble's correction deciding Carry. Negative, Zero and Overflow always come
+    // from the uncorrected result.
+    fn arr(&mut self, mode: &AddressingMode) {
+        let operand = self.register_a & self.get_read_operand_value(mode);
+        let carry = self.is_flag_set(StatusFlag::Carry) as u8;
+        let mut result = operand >> 1 | carry << 7;
+
+        self.update_zero_and_negative_flags(result);
+        self.update_flag(StatusFlag::Overflow, (result ^ operand) & 0b0100_0000 != 0);
+
+        if self.decimal_mode() {
+            let lo = operand & 0x0F;
+            let hi = operand >> 4;
+
+            if lo + (lo & 1) > 5 {
+                result = (result & 0xF0) | (result.wrapping_add(6) & 0x0F);
+            }
+            let high_carry = hi + (hi & 1) > 5;
+            if high_carry {
+                result = result.wrapping_add(0x60);
+            }
+            self.update_flag(StatusFlag::Carry, high_carry);
+        } else {
+            self.update_flag(StatusFlag::Carry, util::get_bit_at(result, 6));
+        }
+
+        self.register_a = result;
+    }
+
+    fn dcp(&mut self, mode: &AddressingMode) {
+        let result = self.get_operand_value(mode).wrapping_sub(1);
+
+        self.set_operand_value(mode, result);
+        self.compare_value(self.register_a, result);
+    }
+
+    fn isc(&mut self, mode: &AddressingMode) {
+        let result = self.get_operand_value(mode).wrapping_add(1);
+
+        self.set_operand_value(mode, result);
+        self.subtract_with_carry(result);
+    }
+
+    // The CPU stops fetching. Leave the program counter on the opcode.
+    fn jam(&mut self) {
+        self.jump(self.program_counter.wrapping_sub(1));
+        self.jammed = true;
+    }
+
+    fn las(&mut self, mode: &AddressingMode) {
+        let result = self.get_read_operand_value(mode) & self.stack_pointer;
+
+        self.register_a = result;
+        self.register_x = result;
+        self.stack_pointer = result;
+        self.update_zero_and_negative_flags(result);
+    }
+
+    fn lax(&mut self, mode: &AddressingMode) {
+        self.lda(mode);
+        self.register_x = self.register_a;
+    }
+
+    fn lxa(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+
+        self.register_a = (self.register_a | self.unstable_magic) & operand;
+        self.register_x = self.register_a;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // The operand, if any, is still read, so indexed modes pay for a page crossing
+    fn nop(&mut self, mode: &AddressingMode) {
+        if *mode != AddressingMode::Implicit {
+            self.get_read_operand_value(mode);
+        }
+    }
+
+    fn rla(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+        let result = self.rotate_left(operand);
+
+        self.set_operand_value(mode, result);
+        self.register_a &= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn rra(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+        let result = self.rotate_right(operand);
+
+        self.set_operand_value(mode, result);
+        self.add_with_carry(result);
+    }
+
+    fn sax(&mut self, mode: &AddressingMode) {
+        self.set_operand_value(mode, self.register_a & self.register_x);
+    }
+
+    fn sbx(&mut self, mode: &AddressingMode) {
+        let operand = self.get_read_operand_value(mode);
+        let register = self.register_a & self.register_x;
+
+        self.compare_value(register, operand);
+        self.register_x = register.wrapping_sub(operand);
+    }
+
+    fn sha(&mut self, mode: &AddressingMode) {
+        self.store_unstable(mode, self.register_a & self.register_x);
+    }
+
+    fn shx(&mut self, mode: &AddressingMode) {
+        self.store_unstable(mode, self.register_x);
+    }
+
+    fn shy(&mut self, mode: &AddressingMode) {
+        self.store_unstable(mode, self.register_y);
+    }
+
+    fn slo(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+        let result = self.shift_left(operand);
+
+        self.set_operand_value(mode, result);
+        self.register_a |= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn sre(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+        let result = self.shift_right(operand);
+
+        self.set_operand_value(mode, result);
+        self.register_a ^= result;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn tas(&mut self, mode: &AddressingMode) {
+        self.stack_pointer = self.register_a & self.register_x;
+        self.store_unstable(mode, self.stack_pointer);
+    }
+
+    // SHA, SHX, SHY and TAS AND the stored value with the high byte of the base address
+    // plus one. When indexing crosses a page, that value also replaces the high byte of
+    // the address written to.
+    fn store_unstable(&mut self, mode: &AddressingMode, value: u8) {
+        let address = self.get_operand_address(mode);
+        let index = match mode {
+            AddressingMode::AbsoluteX => self.register_x,
+            _ => self.register_y,
+        };
+        let base = address.wrapping_sub(index as u16);
+
+        let value = value & ((base >> 8) as u8).wrapping_add(1);
+        let address = if util::is_page_crossed(base, address) {
+            (value as u16) << 8 | (address & 0x00FF)
+        } else {
+            address
+        };
+
+        match &mut self.operand_latch {
+            Some(latched) => *latched = (address, value),
+            None => self.mem_write(address, value),
+        }
+    }
+
+    // 65C02 instructions
+
+    // BBR and BBS test a bit of a zero page location, then branch like the other
+    // branches. The bit number is encoded in the opcode.
+    fn branch_on_bit(&mut self, opcode: u8, mode: &AddressingMode) {
+        let bit = (opcode >> 4) & 0b0111;
+        let branch_if_set = opcode & 0b1000_0000 != 0;
+
+        let operand = self.get_operand_value(mode);
+        self.program_counter = self.program_counter.wrapping_add(1);
+
+        if util::get_bit_at(operand, bit) == branch_if_set {
+            self.branch(&AddressingMode::Relative);
+        } else {
+            self.jump(self.program_counter.wrapping_add(1));
+        }
+    }
+
+    fn bra(&mut self) {
+        self.branch(&AddressingMode::Relative);
+    }
+
+    fn phx(&mut self) {
+        self.stack_push(self.register_x);
+    }
+
+    fn phy(&mut self) {
+        self.stack_push(self.register_y);
+    }
+
+    fn plx(&mut self) {
+        self.register_x = self.stack_pop();
+        self.update_zero_and_negative_flags(self.register_x);
+    }
+
+    fn ply(&mut self) {
+        self.register_y = self.stack_pop();
+        self.update_zero_and_negative_flags(self.register_y);
+    }
+
+    // RMB and SMB clear or set a bit of a zero page location, encoded like BBR and BBS
+    fn modify_bit(&mut self, opcode: u8, mode: &AddressingMode) {
+        let mask = 1 << ((opcode >> 4) & 0b0111);
+        let operand = self.get_operand_value(mode);
+
+        if opcode & 0b1000_0000 != 0 {
+            self.set_operand_value(mode, operand | mask);
+        } else {
+            self.set_operand_value(mode, operand & !mask);
+        }
+    }
+
+    // The CPU stops until the next reset. Leave the program counter on the opcode.
+    fn stp(&mut self) {
+        self.jump(self.program_counter.wrapping_sub(1));
+        self.stopped = true;
+    }
+
+    fn stz(&mut self, mode: &AddressingMode) {
+        self.set_operand_value(mode, 0);
+    }
+
+    // TRB and TSB set Zero like BIT does, from A AND memory before the update
+    fn trb(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+
+        self.update_flag(StatusFlag::Zero, self.register_a & operand == 0);
+        self.set_operand_value(mode, operand & !self.register_a);
+    }
+
+    fn tsb(&mut self, mode: &AddressingMode) {
+        let operand = self.get_operand_value(mode);
+
+        self.update_flag(StatusFlag::Zero, self.register_a & operand == 0);
+        self.set_operand_value(mode, operand | self.register_a);
+    }
+
+    fn wai(&mut self) {
+        self.waiting = true;
+    }
+
+    // Opcodes whose meaning on the 65C02 differs from the NMOS 6502, which are all of
+    // the ones outside the documented set
+    fn execute_cmos(&mut self, opcode: u8, mode: &AddressingMode) {
+        let wdc = self.variant == Variant::Wdc65C02;
+
+        match opcode {
+            // ADC, AND, CMP, EOR, LDA, ORA, SBC, STA (zp)
+            0x72 => self.adc(mode),
+            0x32 => self.and(mode),
+            0xD2 => self.cmp(mode),
+            0x52 => self.eor(mode),
+            0xB2 => self.lda(mode),
+            0x12 => self.ora(mode),
+            0xF2 => self.sbc(mode),
+            0x92 => self.sta(mode),
+
+            // BIT
+            0x89 | 0x34 | 0x3C => self.bit(mode),
+
+            // BRA
+            0x80 => self.bra(),
+
+            // JMP (abs,X)
+            0x7C => self.jmp(mode),
+
+            // DEC, INC
+            0x3A => self.dec(mode),
+            0x1A => self.inc(mode),
+
+            // PHX, PHY, PLX, PLY
+            0xDA => self.phx(),
+            0x5A => self.phy(),
+            0xFA => self.plx(),
+            0x7A => self.ply(),
+
+            // STZ
+            0x64 | 0x74 | 0x9C | 0x9E => self.stz(mode),
+
+            // TRB
+            0x14 | 0x1C => self.trb(mode),
+
+            // TSB
+            0x04 | 0x0C => self.tsb(mode),
+
+            // BBR, BBS
+            _ if wdc && opcode & 0x0F == 0x0F => self.branch_on_bit(opcode, mode),
+
+            // RMB, SMB
+            _ if wdc && opcode & 0x0F == 0x07 => self.modify_bit(opcode, mode),
+
+            // STP
+            0xDB if wdc => self.stp(),
+
+            // WAI
+            0xCB if wdc => self.wai(),
+
+            // NOP
+            _ => self.nop(mode),
+        }
+    }
+
+    fn execute(&mut self, opcode: u8, mode: &AddressingMode) {
+        match opcode {
+            // ADC
+            0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71 => self.adc(mode),
+
+            // AND
+            0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31 => self.and(mode),
+
+            // ASL
+            0x0A | 0x06 | 0x16 | 0x0E | 0x1E => self.asl(mode),
+
+            // BCC
+            0x90 => self.bcc(),
+
+            // BCS
+            0xB0 => self.bcs(),
+
+            // BEQ
+            0xF0 => self.beq(),
+
+            // BIT
+            0x24 | 0x2C => self.bit(mode),
+
+            // BMI
+            0x30 => self.bmi(),
+
+            // BNE
+            0xD0 => self.bne(),
+
+            // BPL
+            0x10 => self.bpl(),
+
+            // BVC
+            0x50 => self.bvc(),
+
+            // BVS
+            0x70 => self.bvs(),
+
+            // CMP
+            0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1 => self.cmp(mode),
+
+            // CPX
+            0xE0 | 0xE4 | 0xEC => self.cpx(mode),
+
+            // CPY
+            0xC0 | 0xC4 | 0xCC => self.cpy(mode),
+
+            // DEC
+            0xC6 | 0xD6 | 0xCE | 0xDE => self.dec(mode),
+
+            // EOR
+            0x49 | 0x45 | 0x55 | 0x4D | 0x5D | 0x59 | 0x41 | 0x51 => self.eor(mode),
+
+            // INC
+            0xE6 | 0xF6 | 0xEE | 0xFE => self.inc(mode),
+
+            // JMP
+            0x4C | 0x6C => self.jmp(mode),
+
+            // JSR
+            0x20 => self.jsr(mode),
+
+            // LDA
+            0xA9 | 0xA5 | 0xB5 | 0xAD | 0xBD | 0xB9 | 0xA1 | 0xB1 => self.lda(mode),
+
+            // LDX
+            0xA2 | 0xA6 | 0xB6 | 0xAE | 0xBE => self.ldx(mode),
+
+            // LDY
+            0xA0 | 0xA4 | 0xB4 | 0xAC | 0xBC => self.ldy(mode),
+
+            // LSR
+            0x4A | 0x46 | 0x56 | 0x4E | 0x5E => self.lsr(mode),
+
+            // ORA
+            0x09 | 0x05 | 0x15 | 0x0D | 0x1D | 0x19 | 0x01 | 0x11 => self.ora(mode),
+
+            // ROL
+            0x2A | 0x26 | 0x36 | 0x2E | 0x3E => self.rol(mode),
+
+            // ROR
+            0x6A | 0x66 | 0x76 | 0x6E | 0x7E => self.ror(mode),
+
+            // SBC
+            0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1 => self.sbc(mode),
+
+            // STA
+            0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91 => self.sta(mode),
+
+            // STX
+            0x86 | 0x96 | 0x8E => self.stx(mode),
+
+            // STY
+            0x84 | 0x94 | 0x8C => self.sty(mode),
+
+            // Implicit addressing opcodes
+            0xAA => self.tax(),
+            0xA8 => self.tay(),
+            0xBA => self.tsx(),
+            0x8A => self.txa(),
+            0x9A => self.txs(),
+            0x98 => self.tya(),
+            0xE8 => self.inx(),
+            0xC8 => self.iny(),
+            0xCA => self.dex(),
+            0x88 => self.dey(),
+            0x18 => self.clc(),
+            0xD8 => self.cld(),
+            0x58 => self.cli(),
+            0xB8 => self.clv(),
+            0x38 => self.sec(),
+            0x78 => self.sei(),
+            0xEA => {}
+            0x48 => self.pha(),
+            0x08 => self.php(),
+            0x68 => self.pla(),
+            0x28 => self.plp(),
+            0x60 => self.rts(),
+            0x40 => self.rti(),
+            0xF8 => self.sed(),
+            0x00 => self.brk(),
+
+            _ if self.variant.is_cmos() => self.execute_cmos(opcode, mode),
+
+            // Undocumented
+
+            // SLO
+            0x07 | 0x17 | 0x0F | 0x1F | 0x1B | 0x03 | 0x13 => self.slo(mode),
+
+            // RLA
+            0x27 | 0x37 | 0x2F | 0x3F | 0x3B | 0x23 | 0x33 => self.rla(mode),
+
+            // SRE
+            0x47 | 0x57 | 0x4F | 0x5F | 0x5B | 0x43 | 0x53 => self.sre(mode),
+
+            // RRA
+            0x67 | 0x77 | 0x6F | 0x7F | 0x7B | 0x63 | 0x73 => self.rra(mode),
+
+            // DCP
+            0xC7 | 0xD7 | 0xCF | 0xDF | 0xDB | 0xC3 | 0xD3 => self.dcp(mode),
+
+            // ISC
+            0xE7 | 0xF7 | 0xEF | 0xFF | 0xFB | 0xE3 | 0xF3 => self.isc(mode),
+
+            // SAX
+            0x87 | 0x97 | 0x8F | 0x83 => self.sax(mode),
+
+            // LAX
+            0xA7 | 0xB7 | 0xAF | 0xBF | 0xA3 | 0xB3 => self.lax(mode),
+
+            // LAS
+            0xBB => self.las(mode),
+
+            // ANC
+            0x0B | 0x2B => self.anc(mode),
+
+            // ALR
+            0x4B => self.alr(mode),
+
+            // ARR
+            0x6B => self.arr(mode),
+
+            // SBX
+            0xCB => self.sbx(mode),
+
+            // USBC
+            0xEB => self.sbc(mode),
+
+            // ANE
+            0x8B => self.ane(mode),
+
+            // LXA
+            0xAB => self.lxa(mode),
+
+            // SHA
+            0x9F | 0x93 => self.sha(mode),
+
+            // SHX
+            0x9E => self.shx(mode),
+
+            // SHY
+            0x9C => self.shy(mode),
+
+            // TAS
+            0x9B => self.tas(mode),
+
+            // NOP
+            0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => {}
+            0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 | 0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74
+            | 0xD4 | 0xF4 | 0x0C | 0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => self.nop(mode),
+
+            // JAM
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => {
+                self.jam()
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), CpuError> {
+        self.run_with_callback(|_| {})
+    }
+
+    // The callback runs before every instruction, which is where devices get the chance
+    // to raise or release the interrupt lines. A line raised there is recognised
+    // before the following instruction.
+    pub fn run_with_callback<F>(&mut self, mut callback: F) -> Result<(), CpuError>
+    where
+        F: FnMut(&mut Self),
+    {
+        loop {
+            self.check_instruction_boundary()?;
+            self.poll_interrupts();
+            callback(self);
+
+            let step = self.execute_next()?;
+            if self.is_stopped(&step) {
+                return Ok(());
+            }
+        }
+    }
+
+    // Services a pending interrupt, then executes exactly one instruction
+    pub fn step(&mut self) -> Result<Step, CpuError> {
+        self.check_instruction_boundary()?;
+        let start = self.cycles;
+        self.poll_interrupts();
+
+        let mut step = self.execute_next()?;
+        step.cycles = self.cycles - start;
+        Ok(step)
+    }
+
+    // Steps until at least `cycles` cycles have passed and returns how many did. The
+    // last instruction is never cut short, so this can overshoot by a few cycles. After
+    // WAI it keeps idling, since a device may raise an interrupt in the meantime.
+    pub fn run_for(&mut self, cycles: u64) -> Result<u64, CpuError> {
+        let start = self.cycles;
+        while self.cycles - start < cycles {
+            let step = self.step()?;
+            if !self.waiting && self.is_stopped(&step) {
+                break;
+            }
+        }
+        Ok(self.cycles - start)
+    }
+
+    // Steps until `predicate` holds after an instruction and returns that instruction.
+    // Like `run`, it also stops on a halting BRK, and on a trap if `stop_on_self_jump`
+    // is set.
+    pub fn run_until<P>(&mut self, mut predicate: P) -> Result<Step, CpuError>
+    where
+        P: FnMut(&Self) -> bool,
+    {
+        loop {
+            let step = self.step()?;
+            if predicate(self) || self.is_stopped(&step) {
+                return Ok(step);
+            }
+        }
+    }
+
+    fn is_stopped(&self, step: &Step) -> bool {
+        step.halted || (self.stop_on_self_jump && self.is_trapped(step))
+    }
+
+    // A jump or branch to itself can never make progress, and neither can WAI, unless
+    // an interrupt breaks out
+    fn is_trapped(&self, step: &Step) -> bool {
+        if self.waiting {
+            return !self.wakes_up();
+        }
+        self.program_counter == step.address && !self.interrupt_pending()
+    }
+
+    // WAI resumes on any interrupt, even an IRQ masked by the I flag. That one is not
+    // serviced; execution just continues after the WAI.
+    fn wakes_up(&self) -> bool {
+        self.irq_line || self.nmi_pending
+    }
+
+    // Looks up the opcode just fetched from `pc`, applying the illegal opcode policy to
+    // anything outside the documented instruction set. Under `Error` and `Jam` the
+    // program counter is left on the opcode.
+    fn decode(&mut self, opcode: u8, pc: u16) -> Result<&'static Instruction, CpuError> {
+        let instruction_map = self.variant.instruction_map();
+        let instruction = instruction_map.get(&opcode);
+        match instruction {
+            Some(instruction) if instruction.documented => return Ok(instruction),
+            _ => {}
+        }
+
+        match (self.illegal_opcode_policy, instruction) {
+            (IllegalOpcodePolicy::Execute, Some(instruction)) => Ok(instruction),
+            // Nothing to execute for an opcode the variant does not decode at all
+            (IllegalOpcodePolicy::Execute | IllegalOpcodePolicy::Error, _) => {
+                self.program_counter = pc;
+                Err(CpuError::IllegalOpcode { opcode, pc })
+            }
+            (IllegalOpcodePolicy::Nop, _) => Ok(instruction_map[&0xEA]),
+            (IllegalOpcodePolicy::Jam, _) => {
+                self.program_counter = pc;
+                self.jammed = true;
+                Err(CpuError::Jammed { pc })
+            }
+        }
+    }
+
+    fn check_bus_fault(&mut self, pc: u16) -> Result<(), CpuError> {
+        match self.bus.take_fault() {
+            Some(address) => Err(CpuError::BusFault { address, pc }),
+            None => Ok(()),
+        }
+    }
+
+    fn execute_next(&mut self) -> Result<Step, CpuError> {
+        if self.jammed {
+            return Err(CpuError::Jammed {
+                pc: self.program_counter,
+            });
+        }
+        if self.stopped {
+            return Err(CpuError::Stopped {
+                pc: self.program_counter,
+            });
+        }
+
+        let start = self.cycles;
+
+        // An interrupt that can be serviced is taken before the next instruction. One
+        // raised since the last poll is left for the next one.
+        if self.waiting {
+            if !self.wakes_up() || self.interrupt_pending() {
+                return Ok(self.idle());
+            }
+            self.waiting = false;
+        }
+
+        // Fetch
+        self.page_crossed = false;
+        self.effective_address = None;
+        self.branch_taken = false;
+        self.jumped = false;
+        let opcode_address = self.program_counter;
+        let opcode = self.mem_read(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+
+        // Decode
+        let instruction = self.decode(opcode, opcode_address)?;
+
+        let halted = opcode == 0x00 && self.halt_on_brk;
+        if !halted {
+            // Execute
+            self.cycles += instruction.cycles as u64;
+            self.execute(instruction.opcode, &instruction.mode);
+            if self.jammed {
+                return Err(CpuError::Jammed { pc: opcode_address });
+            }
+            if self.stopped {
+                return Err(CpuError::Stopped { pc: opcode_address });
+            }
+            if self.decimal_penalty(instruction.mnemonic) {
+                self.cycles += 1;
+            }
+
+            // Instructions that jump have already set the program counter. The others
+            // leave it on their operand.
+            if !self.jumped {
+                self.program_counter = self
+                    .program_counter
+                    .wrapping_add((instruction.length - 1) as u16);
+            }
+        }
+
+        self.check_bus_fault(opcode_address)?;
+
+        Ok(Step {
+            address: opcode_address,
+            opcode,
+            mnemonic: instruction.mnemonic,
+            mode: instruction.mode,
+            effective_address: self.effective_address,
+            cycles: self.cycles - start,
+            branch_taken: self.branch_taken,
+            halted,
+        })
+    }
+
+    // One cycle spent waiting after WAI. It is reported as the WAI instruction.
+    fn idle(&mut self) -> Step {
+        let address = self.program_counter.wrapping_sub(1);
+        let instruction = self.variant.instruction_map()[&0xCB];
+        self.cycles += 1;
+
+        Step {
+            address,
+            opcode: instruction.opcode,
+            mnemonic: instruction.mnemonic,
+            mode: instruction.mode,
+            effective_address: None,
+            cycles: 1,
+            branch_taken: false,
+            halted: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_0xa9_lda_immediate_load_data() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x05, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x05);
+        assert!(cpu.status & 0b0000_0010 == 0b00);
+        assert!(cpu.status & 0b1000_0000 == 0);
+    }
+
+    #[test]
+    fn test_0xa9_lda_zero_flag() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x00, 0x00]).unwrap();
+        assert!(cpu.status & 0b0000_0010 == 0b10);
+    }
+
+    #[test]
+    fn test_0xaa_tax_move_a_to_x() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x10, 0xaa, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0x10);
+    }
+
+    #[test]
+    fn test_0xe8_inx_increments_base() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xe8, 0x00]).unwrap();
+
+        assert_eq!(cpu.register_x, 1)
+    }
+
+    #[test]
+    fn test_0xe8_inx_increments_wraps() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xA9, 0xFF, 0xAA, 0xE8, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0b0000_0000);
+        assert!(cpu.status & (StatusFlag::Zero as u8) == StatusFlag::Zero as u8);
+    }
+
+    #[test]
+    fn test_0xe8_inx_set_negative_flag() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.register_x = 0b0111_1111;
+        cpu.load_and_run(vec![0xA9, 0b0111_1111, 0xAA, 0xE8, 0xe8, 0x00])
+            .unwrap();
+        assert!(cpu.status & (StatusFlag::Negative as u8) == StatusFlag::Negative as u8);
+    }
+
+    #[test]
+    fn test_0xe8_inx_no_negative_flag() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.register_x = 0b0111_1110;
+        cpu.load_and_run(vec![0xe8, 0x00]).unwrap();
+        assert!(cpu.status & (StatusFlag::Negative as u8) == 0);
+    }
+
+    #[test]
+    fn test_5_ops_working_together() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0xc1)
+    }
+
+    #[test]
+    fn test_inx_overflow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xff, 0xaa, 0xe8, 0xe8, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 1)
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Error;
+        let result = cpu.load_and_run(vec![0xe8, 0x02, 0x00]);
+        assert_eq!(
+            result,
+            Err(CpuError::IllegalOpcode {
+                opcode: 0x02,
+                pc: 0x8001
+            })
+        );
+        assert_eq!(cpu.program_counter, 0x8001);
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_unknown_opcode_as_nop() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Nop;
+        cpu.load(vec![0x02, 0xe8, 0x00]);
+        cpu.reset();
+
+        let step = cpu.step().unwrap();
+        assert_eq!(step.opcode, 0x02);
+        assert_eq!(step.mnemonic, "NOP");
+        assert_eq!(step.cycles, 2);
+
+        cpu.run().unwrap();
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_unknown_opcode_jams() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Jam;
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.load(vec![0x02, 0xe8, 0x00]);
+        cpu.reset();
+
+        assert_eq!(cpu.run(), Err(CpuError::Jammed { pc: 0x8000 }));
+
+        // Not even an NMI gets it going again
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step(), Err(CpuError::Jammed { pc: 0x8000 }));
+        assert_eq!(cpu.register_x, 0);
+
+        cpu.reset();
+        assert_eq!(cpu.step(), Err(CpuError::Jammed { pc: 0x8000 }));
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Nop;
+        cpu.reset();
+        cpu.run().unwrap();
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_every_opcode_steps_without_panicking() {
+        let variants = [
+            Variant::Nmos6502,
+            Variant::Ricoh2A03,
+            Variant::Cmos65C02,
+            Variant::Wdc65C02,
+        ];
+        for variant in variants {
+            for opcode in 0..=0xFF {
+                let mut cpu = CPU::new(variant);
+                cpu.load(vec![opcode, 0x10, 0x20]);
+                cpu.reset();
+
+                match cpu.step() {
+                    Ok(step) => assert_eq!(step.opcode, opcode),
+                    Err(error) => match variant.instruction_map()[&opcode].mnemonic {
+                        "JAM" => assert_eq!(error, CpuError::Jammed { pc: 0x8000 }),
+                        "STP" => assert_eq!(error, CpuError::Stopped { pc: 0x8000 }),
+                        mnemonic => panic!("{} failed with {:?}", mnemonic, error),
+                    },
+                }
+            }
+        }
+    }
+
+    // Undocumented instructions
+
+    #[test]
+    fn test_0xa7_lax_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x80);
+        cpu.load_and_run(vec![0xa7, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert_eq!(cpu.register_x, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x87_sax_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xF0, 0xa2, 0x3C, 0x87, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x30);
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x07_slo_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x81);
+        cpu.load_and_run(vec![0xa9, 0x04, 0x07, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x02);
+        assert_eq!(cpu.register_a, 0x06);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x27_rla_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x81);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x0F, 0x27, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x03);
+        assert_eq!(cpu.register_a, 0x03);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x47_sre_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x03);
+        cpu.load_and_run(vec![0xa9, 0x01, 0x47, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x01);
+        assert_eq!(cpu.register_a, 0x00);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x67_rra_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x02);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x10, 0x67, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x81);
+        assert_eq!(cpu.register_a, 0x91);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xc7_dcp_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x43);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xc7, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x42);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe7_isc_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x0F);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x20, 0xe7, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x10);
+        assert_eq!(cpu.register_a, 0x10);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xdf_dcp_absolute_x_cycles() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // No page crossing, but read-modify-write always takes the fix-up cycle
+        cpu.load_and_run(vec![0xdf, 0x00, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1200), 0xFF);
+        assert_eq!(cpu.cycles, 7 + 7);
+    }
+
+    #[test]
+    fn test_0xbb_las_absolute_y() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1201, 0xF0);
+        cpu.load_and_run(vec![0xa0, 0x01, 0xbb, 0x00, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xF0);
+        assert_eq!(cpu.register_x, 0xF0);
+        assert_eq!(cpu.stack_pointer, 0xF0);
+    }
+
+    #[test]
+    fn test_0x0b_anc_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x4b_alr_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x4b, 0x03, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x01);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x6b_arr_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x40, 0x6b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x20);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+
+        cpu.load_and_run(vec![0x38, 0xa9, 0xFF, 0x6b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xFF);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x6b_arr_decimal() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0xFF, 0x6b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xD5);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xcb_sbx_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0xa2, 0x0F, 0xcb, 0x05, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0x0A);
+        assert_eq!(cpu.register_a, 0xFF);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xeb_usbc_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x10, 0xeb, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0F);
+    }
+
+    #[test]
+    fn test_0x8b_ane_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x00, 0xa2, 0xFF, 0x8b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xEE);
+
+        cpu.unstable_magic = 0xFF;
+        cpu.load_and_run(vec![0xa9, 0x00, 0xa2, 0xFF, 0x8b, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xFF);
+    }
+
+    #[test]
+    fn test_0xab_lxa_immediate() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x01, 0xab, 0x0F, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0F);
+        assert_eq!(cpu.register_x, 0x0F);
+    }
+
+    #[test]
+    fn test_0x9e_shx_absolute_y() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x01, 0xa2, 0x7F, 0x9e, 0x00, 0x12, 0x00])
+            .unwrap();
+        // $12 + 1
+        assert_eq!(cpu.mem_read(0x1201), 0x13);
+    }
+
+    #[test]
+    fn test_0x9e_shx_absolute_y_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x10, 0xa2, 0x05, 0x9e, 0xF8, 0x12, 0x00])
+            .unwrap();
+        // The stored value becomes the high byte of the address
+        assert_eq!(cpu.mem_read(0x0108), 0x01);
+        assert_eq!(cpu.mem_read(0x1308), 0x00);
+    }
+
+    #[test]
+    fn test_0x9c_shy_absolute_x() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x01, 0xa0, 0xFF, 0x9c, 0x00, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1201), 0x13);
+    }
+
+    #[test]
+    fn test_0x93_sha_indirect_y() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1200);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0xa2, 0x3F, 0xa0, 0x01, 0x93, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1201), 0x13);
+    }
+
+    #[test]
+    fn test_0x9b_tas_absolute_y() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0xa2, 0x31, 0x9b, 0x00, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.stack_pointer, 0x31);
+        assert_eq!(cpu.mem_read(0x1200), 0x11);
+    }
+
+    #[test]
+    fn test_0x1c_nop_absolute_x_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x20, 0x1c, 0xF0, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.program_counter, 0x8006);
+        assert_eq!(cpu.cycles, 7 + 2 + 5);
+    }
+
+    #[test]
+    fn test_0x02_jam() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        let result = cpu.load_and_run(vec![0xe8, 0x02, 0xe8, 0x00]);
+        assert_eq!(result, Err(CpuError::Jammed { pc: 0x8001 }));
+        assert_eq!(cpu.program_counter, 0x8001);
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    // ADC + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0x69_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x10, 0x69, 0x20, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x30);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x69_adc_carry_out() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x69, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0x69_adc_carry_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 0xFF + 0x01 sets the carry, which is then added in by the second ADC
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x69, 0x01, 0x69, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 2);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x69_adc_overflow_positive() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x50, 0x69, 0x50, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xA0);
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x69_adc_overflow_negative() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xD0, 0x69, 0x90, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x60);
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x69_adc_no_overflow_mixed_signs() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x50, 0x69, 0xD0, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x20);
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Zero Page
+    #[test]
+    fn test_0x65_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x22);
+        cpu.load_and_run(vec![0xa9, 0x11, 0x65, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0x75_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x22);
+        cpu.load_and_run(vec![0xa2, 2, 0xa9, 0x11, 0x75, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0x6d_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x22);
+        cpu.load_and_run(vec![0xa9, 0x11, 0x6d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0x7d_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x22);
+        cpu.load_and_run(vec![0xa2, 1, 0xa9, 0x11, 0x7d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0x79_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0x22);
+        cpu.load_and_run(vec![0xa0, 3, 0xa9, 0x11, 0x79, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0x61_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0x22);
+        cpu.load_and_run(vec![0xa2, 4, 0xa9, 0x11, 0x61, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0x71_adc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0x22);
+        cpu.load_and_run(vec![0xa0, 4, 0xa9, 0x11, 0x71, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x33);
+    }
+
+    // AND + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0x29_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1010_1101, 0x29, 0b1111_1110, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b1010_1100);
+        assert_eq!(
+            cpu.status & (StatusFlag::Negative as u8),
+            StatusFlag::Negative as u8
+        );
+        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+    }
+
+    // Zero Page
+    #[test]
+    fn test_0x25_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![
+            0xa9,
+            0xFF,
+            0x85,
+            0x10,
+            0xa9,
+            0b1111_0111,
+            0x25,
+            0x10,
+            0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0b1111_0111);
+        assert_eq!(
+            cpu.status & (StatusFlag::Negative as u8),
+            StatusFlag::Negative as u8
+        );
+        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0x35_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![
+            0xa9,
+            0xFF,
+            0x85,
+            0x10,
+            0xa9,
+            0b1111_0111,
+            0x35,
+            0x10,
+            0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0b1111_0111);
+        assert_eq!(
+            cpu.status & (StatusFlag::Negative as u8),
+            StatusFlag::Negative as u8
+        );
+        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0x2d_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b1111_1111);
+        cpu.load_and_run(vec![0xa9, 0b1010_1010, 0x2d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b1010_1010);
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0x3d_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x4321, 0b1111_1111);
+        // Load A with 42. Load X with 1. Address 0x4320 + 1 (see above). AND with A (42)
+        cpu.load_and_run(vec![0xa9, 42, 0xa2, 1, 0x3d, 0x20, 0x43, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 42);
+    }
+
+    // Absoulute Y
+    #[test]
+    fn test_0x39_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x4321, 0b0000_1000);
+        // Load A with bits. Load Y with 3. Address 0x431E + Y (see above). AND that with A's bits
+        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa0, 3, 0x39, 0x1E, 0x43, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 8);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0x21_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0b0010_0010);
+        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa2, 4, 0x21, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 2);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0x31_and() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0b0010_0010);
+        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa0, 4, 0x31, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 2);
+    }
+
+    #[test]
+    fn test_0x0a_asl() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 2, 0x0a, 0x0a, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 8);
+        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+        assert_eq!(cpu.status & (StatusFlag::Negative as u8), 0);
+        assert_eq!(cpu.status & (StatusFlag::Carry as u8), 0);
+    }
+
+    #[test]
+    fn test_0x0a_asl_overflow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0b1111_1110);
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x0e_asl() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b0111_0011);
+        cpu.load_and_run(vec![0x0e, 0x34, 0x12, 0x00]).unwrap();
+
+        let result = cpu.mem_read(0x1234);
+        assert_eq!(result, 0b1110_0110);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+    // Other ASL: 0x06, 0x16, 0x1E
+
+    #[test]
+    fn test_0x90_bcc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x90, 3, 0xa9, 123, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0);
+    }
+
+    #[test]
+    fn test_0x90_bcc_no_branch() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0x90, 3, 0xa9, 123, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 123);
+    }
+
+    #[test]
+    fn test_0xb0_bcs() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0xb0, 2, 0xa9, 123, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xFF << 1);
+    }
+
+    #[test]
+    fn test_0xb0_bcs_no_branch() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xb0, 3, 0xa9, 123, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 123);
+    }
+
+    #[test]
+    fn test_0xf0_beq() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0, 0xf0, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.register_x, 0x15);
+    }
+
+    #[test]
+    fn test_0xd0_bne() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 1, 0xd0, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 1);
+        assert_eq!(cpu.register_x, 0x15);
+    }
+
+    #[test]
+    fn test_0x10_bpl() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 1, 0x10, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 1);
+        assert_eq!(cpu.register_x, 0x15);
+    }
+
+    #[test]
+    fn test_0x50_bvc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 69, 0x50, 3, 0xa9, 96, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 69);
+    }
+
+    #[test]
+    fn test_0x70_bvs() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x1234, 0xff);
+        cpu.load_and_run(vec![0xa9, 69, 0x2c, 0x34, 0x12, 0x70, 3, 0xa9, 96, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 69);
+    }
+
+    #[test]
+    fn test_0x2c_bit() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0xff);
+        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0x2c_bit_2() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0xff);
+        cpu.load_and_run(vec![0x2c, 0x34, 0x12]).unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0x2c_bit_3() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b_0011_1111);
+        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0x2c_bit_4() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b_0111_1111);
+        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+    }
+    // Other BIT: 0x24
+
+    #[test]
+    fn test_0x30_bmi() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0xff, 0x30, 3, 0xa2, 69, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0xff);
+    }
+
+    #[test]
+    fn test_0xa2_ldx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0xee, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0xee);
+    }
+
+    #[test]
+    fn test_0xa0_ldy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0x12);
+    }
+
+    #[test]
+    fn test_0x85_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x85, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0xFF);
+    }
+
+    // SBC + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0xe9_sbc_borrow_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Carry is clear after reset, so one extra is borrowed
+        cpu.load_and_run(vec![0xa9, 0x10, 0xe9, 0x05, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0A);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_no_borrow_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // ASL of 0x80 sets the carry and leaves A at 0
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x10, 0xe9, 0x05, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0B);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_borrow_out() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x05, 0xe9, 0x06, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0xFF);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_zero() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x42, 0xe9, 0x42, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_overflow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // -128 - 1 does not fit in a signed byte
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x80, 0xe9, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x7F);
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_overflow_positive() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 127 - (-1) does not fit in a signed byte
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x7F, 0xe9, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    // Zero Page
+    #[test]
+    fn test_0xe5_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x11);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x33, 0xe5, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0xf5_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x11);
+        cpu.load_and_run(vec![
+            0xa9, 0x80, 0x0a, 0xa2, 2, 0xa9, 0x33, 0xf5, 0x10, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0xed_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x11);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x0a, 0xa9, 0x33, 0xed, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0xfd_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x11);
+        cpu.load_and_run(vec![
+            0xa9, 0x80, 0x0a, 0xa2, 1, 0xa9, 0x33, 0xfd, 0x34, 0x12, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0xf9_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0x11);
+        cpu.load_and_run(vec![
+            0xa9, 0x80, 0x0a, 0xa0, 3, 0xa9, 0x33, 0xf9, 0x34, 0x12, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0xe1_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0x11);
+        cpu.load_and_run(vec![
+            0xa9, 0x80, 0x0a, 0xa2, 4, 0xa9, 0x33, 0xe1, 0x10, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0xf1_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0x11);
+        cpu.load_and_run(vec![
+            0xa9, 0x80, 0x0a, 0xa0, 4, 0xa9, 0x33, 0xf1, 0x10, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_a, 0x22);
+    }
+
+    // Decimal mode
+
+    #[test]
+    fn test_0xf8_sed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0x00]).unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Decimal));
+    }
+
+    #[test]
+    fn test_0xd8_cld() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xd8, 0x00]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Decimal));
+    }
+
+    #[test]
+    fn test_0x69_adc_decimal() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x58, 0x69, 0x46, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x04);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x69_adc_decimal_carry_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 0x80 << 1 sets the carry and clears A
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x80, 0x0a, 0xa9, 0x12, 0x69, 0x34, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x47);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x69_adc_decimal_nmos_flags() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 99 + 01 = 00 with carry, but Z reflects the binary sum (0x9A) and N the
+        // intermediate result (0xA0)
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x99, 0x69, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x00);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+    }
+
+    #[test]
+    fn test_0x69_adc_decimal_overflow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x79, 0x69, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x69_adc_decimal_invalid_bcd() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Non-BCD operands still produce the bytes real silicon does
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x0F, 0x69, 0x0F, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x14);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_decimal() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x80, 0x0a, 0xa9, 0x46, 0xe9, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x34);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_decimal_half_borrow() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x80, 0x0a, 0xa9, 0x40, 0xe9, 0x13, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x27);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_decimal_borrow_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x32, 0xe9, 0x02, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x29);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0xe9_sbc_decimal_borrow_out() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 00 - 01 = 99 with a borrow; N and Z follow the binary result (0xFF)
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x80, 0x0a, 0xa9, 0x00, 0xe9, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x99);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    // CMP + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0xc9_cmp_equal() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xc9, 0x42, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_0xc9_cmp_greater() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xc9, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xc9_cmp_less() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x10, 0xc9, 0x42, 0x00])
+            .unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xc9_cmp_is_unsigned() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 0xFF is greater than 0x01 even though it is negative as a signed byte
+        cpu.load_and_run(vec![0xa9, 0xFF, 0xc9, 0x01, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    // Zero Page
+    #[test]
+    fn test_0xc5_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x42);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xc5, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0xd5_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x42);
+        cpu.load_and_run(vec![0xa2, 2, 0xa9, 0x42, 0xd5, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Absolute
+    #[test]
+    fn test_0xcd_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x42);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xcd, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0xdd_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x42);
+        cpu.load_and_run(vec![0xa2, 1, 0xa9, 0x42, 0xdd, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0xd9_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0x42);
+        cpu.load_and_run(vec![0xa0, 3, 0xa9, 0x42, 0xd9, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0xc1_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0x42);
+        cpu.load_and_run(vec![0xa2, 4, 0xa9, 0x42, 0xc1, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0xd1_cmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0x42);
+        cpu.load_and_run(vec![0xa0, 4, 0xa9, 0x42, 0xd1, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // CPX + Addressing Modes
+
+    #[test]
+    fn test_0xe0_cpx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x10, 0xe0, 0x20, 0x00])
+            .unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xe4_cpx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x05);
+        cpu.load_and_run(vec![0xa2, 0x05, 0xe4, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xec_cpx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x05);
+        cpu.load_and_run(vec![0xa2, 0x06, 0xec, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_cpx_loop() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Count X up to 5: INX, CPX #5, BNE back to INX
+        cpu.load_and_run(vec![0xa2, 0x00, 0xe8, 0xe0, 0x05, 0xd0, 0xfb, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 5);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    // CPY + Addressing Modes
+
+    #[test]
+    fn test_0xc0_cpy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x20, 0xc0, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xc4_cpy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x05);
+        cpu.load_and_run(vec![0xa0, 0x05, 0xc4, 0x10, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xcc_cpy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x80);
+        cpu.load_and_run(vec![0xa0, 0x01, 0xcc, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    // Stack
+
+    #[test]
+    fn test_reset_stack_pointer() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.stack_pointer = 0x00;
+        cpu.load_and_run(vec![0x00]).unwrap();
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn test_stack_push_pop_u16() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.stack_push_u16(0x1234);
+        assert_eq!(cpu.stack_pointer, 0xFB);
+        assert_eq!(cpu.mem_read(0x01FD), 0x12);
+        assert_eq!(cpu.mem_read(0x01FC), 0x34);
+        assert_eq!(cpu.stack_pop_u16(), 0x1234);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn test_stack_wraps_within_page_one() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.stack_pointer = 0x00;
+        cpu.stack_push(0xAB);
+        cpu.stack_push(0xCD);
+        assert_eq!(cpu.stack_pointer, 0xFE);
+        assert_eq!(cpu.mem_read(0x0100), 0xAB);
+        assert_eq!(cpu.mem_read(0x01FF), 0xCD);
+        assert_eq!(cpu.stack_pop(), 0xCD);
+        assert_eq!(cpu.stack_pop(), 0xAB);
+        assert_eq!(cpu.stack_pointer, 0x00);
+    }
+
+    #[test]
+    fn test_0x48_pha() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0x48, 0x00]).unwrap();
+        assert_eq!(cpu.stack_pointer, 0xFC);
+        assert_eq!(cpu.mem_read(0x01FD), 0x42);
+    }
+
+    #[test]
+    fn test_0x68_pla() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x80, 0x48, 0xa9, 0x00, 0x68, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x08_php() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xf8, 0x08, 0x00]).unwrap();
+        assert_eq!(cpu.stack_pointer, 0xFC);
+        assert_eq!(cpu.mem_read(0x01FD), 0b0011_1100);
+    }
+
+    #[test]
+    fn test_0x28_plp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0xFF, 0x48, 0x28, 0x00])
+            .unwrap();
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert_eq!(cpu.status, 0b1110_1111);
+    }
+
+    #[test]
+    fn test_php_plp_round_trip() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // SED, LDA #$80 (N), PHP, CLD, LDA #$01, PLP
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x80, 0x08, 0xd8, 0xa9, 0x01, 0x28, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Decimal));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Break));
+    }
+
+    // Jumps and subroutines
+
+    #[test]
+    fn test_0x4c_jmp() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x4c, 0x05, 0x80, 0xa9, 0x01, 0xa2, 0x02, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.register_x, 2);
+    }
+
+    #[test]
+    fn test_0x4c_jmp_to_next_byte() {
+        // A jump that lands one byte past its opcode must not skip the operand again
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0x4c, 0x01, 0x80]);
+        cpu.reset();
+        cpu.step().unwrap();
+        assert_eq!(cpu.program_counter, 0x8001);
+    }
+
+    #[test]
+    fn test_0x20_jsr_to_next_byte() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0x20, 0x01, 0x80]);
+        cpu.reset();
+        cpu.step().unwrap();
+        assert_eq!(cpu.program_counter, 0x8001);
+        assert_eq!(cpu.stack_pointer, 0xFB);
+    }
+
+    #[test]
+    fn test_0x6c_jmp_indirect() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x0120, 0x8005);
+        cpu.load_and_run(vec![0x6c, 0x20, 0x01, 0xa9, 0x01, 0xa2, 0x02, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.register_x, 2);
+    }
+
+    #[test]
+    fn test_0x6c_jmp_indirect_page_wrap_bug() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x02FF, 0x05);
+        cpu.mem_write(0x0200, 0x80);
+        cpu.mem_write(0x0300, 0x90);
+        cpu.load_and_run(vec![0x6c, 0xFF, 0x02, 0xa9, 0x01, 0xa2, 0x02, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.register_x, 2);
+    }
+
+    #[test]
+    fn test_0x20_jsr_pushes_return_address_minus_one() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x20, 0x04, 0x80, 0x00, 0x00])
+            .unwrap();
+        assert_eq!(cpu.stack_pointer, 0xFB);
+        assert_eq!(cpu.mem_read(0x01FD), 0x80);
+        assert_eq!(cpu.mem_read(0x01FC), 0x02);
+    }
+
+    #[test]
+    fn test_0x60_rts() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // JSR to a subroutine that loads A, then return and load X
+        cpu.load_and_run(vec![0x20, 0x06, 0x80, 0xa2, 0x01, 0x00, 0xa9, 0x42, 0x60])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.register_x, 0x01);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn test_nested_jsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // 8000: JSR $8004; BRK
+        // 8004: JSR $8009; INX; RTS
+        // 8009: INX; RTS
+        cpu.load_and_run(vec![
+            0x20, 0x04, 0x80, 0x00, 0x20, 0x09, 0x80, 0xe8, 0x60, 0xe8, 0x60,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_x, 2);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    // LDA + Addressing Modes
+
+    // Zero Page
+    #[test]
+    fn test_0xa5_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x55);
+        cpu.load_and_run(vec![0xa5, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0xb5_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x55);
+        cpu.load_and_run(vec![0xa2, 2, 0xb5, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_0xb5_lda_wraps_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x0F, 0x55);
+        cpu.load_and_run(vec![0xa2, 0xFF, 0xb5, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0xad_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x80);
+        cpu.load_and_run(vec![0xad, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0xbd_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1300, 0x55);
+        cpu.load_and_run(vec![0xa2, 0x01, 0xbd, 0xFF, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0xb9_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0x55);
+        cpu.load_and_run(vec![0xa0, 3, 0xb9, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0xa1_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0x55);
+        cpu.load_and_run(vec![0xa2, 4, 0xa1, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0xb1_lda() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0x55);
+        cpu.load_and_run(vec![0xa0, 4, 0xb1, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    // LDX + Addressing Modes
+
+    #[test]
+    fn test_0xa6_ldx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x55);
+        cpu.load_and_run(vec![0xa6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0x55);
+    }
+
+    #[test]
+    fn test_0xb6_ldx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x55);
+        cpu.load_and_run(vec![0xa0, 2, 0xb6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0x55);
+    }
+
+    #[test]
+    fn test_0xae_ldx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x00);
+        cpu.load_and_run(vec![0xa2, 0x01, 0xae, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0x00);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xbe_ldx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0x55);
+        cpu.load_and_run(vec![0xa0, 3, 0xbe, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0x55);
+    }
+
+    // LDY + Addressing Modes
+
+    #[test]
+    fn test_0xa4_ldy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x55);
+        cpu.load_and_run(vec![0xa4, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0x55);
+    }
+
+    #[test]
+    fn test_0xb4_ldy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x55);
+        cpu.load_and_run(vec![0xa2, 2, 0xb4, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0x55);
+    }
+
+    #[test]
+    fn test_0xac_ldy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x80);
+        cpu.load_and_run(vec![0xac, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xbc_ldy() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x55);
+        cpu.load_and_run(vec![0xa2, 1, 0xbc, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_y, 0x55);
+    }
+
+    // STA + Addressing Modes
+
+    #[test]
+    fn test_0x95_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 2, 0xa9, 0x55, 0x95, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0x55);
+    }
+
+    #[test]
+    fn test_0x8d_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x55, 0x8d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x55);
+    }
+
+    #[test]
+    fn test_0x9d_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 1, 0xa9, 0x55, 0x9d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0x55);
+    }
+
+    #[test]
+    fn test_0x99_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 3, 0xa9, 0x55, 0x99, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1237), 0x55);
+    }
+
+    #[test]
+    fn test_0x81_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.load_and_run(vec![0xa2, 4, 0xa9, 0x55, 0x81, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x55);
+    }
+
+    #[test]
+    fn test_0x91_sta() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.load_and_run(vec![0xa0, 4, 0xa9, 0x55, 0x91, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1238), 0x55);
+    }
+
+    #[test]
+    fn test_sta_does_not_affect_flags() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x00, 0x8d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    // STX + Addressing Modes
+
+    #[test]
+    fn test_0x86_stx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x55, 0x86, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x55);
+    }
+
+    #[test]
+    fn test_0x96_stx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 2, 0xa2, 0x55, 0x96, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0x55);
+    }
+
+    #[test]
+    fn test_0x8e_stx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x55, 0x8e, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x55);
+    }
+
+    // STY + Addressing Modes
+
+    #[test]
+    fn test_0x84_sty() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x55, 0x84, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x55);
+    }
+
+    #[test]
+    fn test_0x94_sty() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 2, 0xa0, 0x55, 0x94, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0x55);
+    }
+
+    #[test]
+    fn test_0x8c_sty() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x55, 0x8c, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x55);
+    }
+
+    #[test]
+    fn test_table_lookup() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Copy four bytes from $0200 to $0300 using LDA abs,X / STA abs,X
+        for (i, byte) in [0xDE, 0xAD, 0xBE, 0xEF].iter().enumerate() {
+            cpu.mem_write(0x0200 + i as u16, *byte);
+        }
+        cpu.load_and_run(vec![
+            0xa2, 0x00, 0xbd, 0x00, 0x02, 0x9d, 0x00, 0x03, 0xe8, 0xe0, 0x04, 0xd0, 0xf5, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.mem_read(0x0300), 0xDE);
+        assert_eq!(cpu.mem_read(0x0301), 0xAD);
+        assert_eq!(cpu.mem_read(0x0302), 0xBE);
+        assert_eq!(cpu.mem_read(0x0303), 0xEF);
+    }
+
+    // ORA + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0x09_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1000_0001, 0x09, 0b0000_0110, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b1000_0111);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x09_ora_zero() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x00, 0x09, 0x00, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    // Zero Page
+    #[test]
+    fn test_0x05_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0b0000_1111);
+        cpu.load_and_run(vec![0xa9, 0b0011_0000, 0x05, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0x15_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 2, 0xa9, 0b0011_0000, 0x15, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0x0d_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b0000_1111);
+        cpu.load_and_run(vec![0xa9, 0b0011_0000, 0x0d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0x1d_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 1, 0xa9, 0b0011_0000, 0x1d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0x19_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0b0000_1111);
+        cpu.load_and_run(vec![0xa0, 3, 0xa9, 0b0011_0000, 0x19, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0x01_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 4, 0xa9, 0b0011_0000, 0x01, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0x11_ora() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0b0000_1111);
+        cpu.load_and_run(vec![0xa0, 4, 0xa9, 0b0011_0000, 0x11, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1111);
+    }
+
+    // EOR + Addressing Modes
+
+    // Immediate
+    #[test]
+    fn test_0x49_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1010_1010, 0x49, 0b0110_0110, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b1100_1100);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x49_eor_zero() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x5A, 0x49, 0x5A, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
     }
 
     // Zero Page
     #[test]
-    fn test_0x25_and() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![
-            0xa9,
-            0xFF,
-            0x85,
-            0x10,
-            0xa9,
-            0b1111_0111,
-            0x25,
-            0x10,
+    fn test_0x45_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0b0000_1111);
+        cpu.load_and_run(vec![0xa9, 0b0011_0011, 0x45, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Zero Page X
+    #[test]
+    fn test_0x55_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 2, 0xa9, 0b0011_0011, 0x55, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Absolute
+    #[test]
+    fn test_0x4d_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b0000_1111);
+        cpu.load_and_run(vec![0xa9, 0b0011_0011, 0x4d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Absolute X
+    #[test]
+    fn test_0x5d_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 1, 0xa9, 0b0011_0011, 0x5d, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Absolute Y
+    #[test]
+    fn test_0x59_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1237, 0b0000_1111);
+        cpu.load_and_run(vec![0xa0, 3, 0xa9, 0b0011_0011, 0x59, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Indexed Indirect (Indirect X)
+    #[test]
+    fn test_0x41_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0b0000_1111);
+        cpu.load_and_run(vec![0xa2, 4, 0xa9, 0b0011_0011, 0x41, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // Indirect Indexed (Indirect Y)
+    #[test]
+    fn test_0x51_eor() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x1234);
+        cpu.mem_write(0x1238, 0b0000_1111);
+        cpu.load_and_run(vec![0xa0, 4, 0xa9, 0b0011_0011, 0x51, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0011_1100);
+    }
+
+    // LSR + Addressing Modes
+
+    #[test]
+    fn test_0x4a_lsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1000_0011, 0x4a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0100_0001);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x4a_lsr_zero() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x01, 0x4a, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x46_lsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0b0000_0100);
+        cpu.load_and_run(vec![0x46, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b0000_0010);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x56_lsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0b0000_0100);
+        cpu.load_and_run(vec![0xa2, 2, 0x56, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0b0000_0010);
+    }
+
+    #[test]
+    fn test_0x4e_lsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b0000_0100);
+        cpu.load_and_run(vec![0x4e, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0b0000_0010);
+    }
+
+    #[test]
+    fn test_0x5e_lsr() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0b0000_0100);
+        cpu.load_and_run(vec![0xa2, 1, 0x5e, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0b0000_0010);
+    }
+
+    // ROL + Addressing Modes
+
+    #[test]
+    fn test_0x2a_rol() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1000_0001, 0x2a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0000_0010);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x2a_rol_carry_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b1000_0001, 0x2a, 0x2a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0000_0101);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x26_rol() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0b0100_0000);
+        cpu.load_and_run(vec![0x26, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b1000_0000);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x36_rol() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0b0000_0001);
+        cpu.load_and_run(vec![0xa2, 2, 0x36, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0b0000_0010);
+    }
+
+    #[test]
+    fn test_0x2e_rol() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b1000_0000);
+        cpu.load_and_run(vec![0x2e, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x3e_rol() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0b0000_0001);
+        cpu.load_and_run(vec![0xa2, 1, 0x3e, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0b0000_0010);
+    }
+
+    // ROR + Addressing Modes
+
+    #[test]
+    fn test_0x6a_ror() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b0000_0011, 0x6a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b0000_0001);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x6a_ror_carry_in() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0b0000_0011, 0x6a, 0x6a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0b1000_0000);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x66_ror() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0b0000_0010);
+        cpu.load_and_run(vec![0x66, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b0000_0001);
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x76_ror() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0b0000_0010);
+        cpu.load_and_run(vec![0xa2, 2, 0x76, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0b0000_0001);
+    }
+
+    #[test]
+    fn test_0x6e_ror() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0b0000_0001);
+        cpu.load_and_run(vec![0x6e, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x7e_ror() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0b0000_0010);
+        cpu.load_and_run(vec![0xa2, 1, 0x7e, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0b0000_0001);
+    }
+
+    // Increment and decrement
+
+    #[test]
+    fn test_0xe6_inc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x7F);
+        cpu.load_and_run(vec![0xe6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xf6_inc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0xFF);
+        cpu.load_and_run(vec![0xa2, 2, 0xf6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0x00);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xee_inc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x41);
+        cpu.load_and_run(vec![0xee, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x42);
+    }
+
+    #[test]
+    fn test_0xfe_inc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x41);
+        cpu.load_and_run(vec![0xa2, 1, 0xfe, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0x42);
+    }
+
+    #[test]
+    fn test_0xc6_dec() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x10, 0x01);
+        cpu.load_and_run(vec![0xc6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x00);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xd6_dec() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x12, 0x00);
+        cpu.load_and_run(vec![0xa2, 2, 0xd6, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x12), 0xFF);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xce_dec() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1234, 0x43);
+        cpu.load_and_run(vec![0xce, 0x34, 0x12, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x1234), 0x42);
+    }
+
+    #[test]
+    fn test_0xde_dec() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x1235, 0x43);
+        cpu.load_and_run(vec![0xa2, 1, 0xde, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x1235), 0x42);
+    }
+
+    #[test]
+    fn test_0xc8_iny() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0xFF, 0xc8, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0xca_dex() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xca, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0xFF);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x88_dey() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x01, 0x88, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_dex_loop() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Count A up while X counts down from 3 to 0
+        cpu.load_and_run(vec![0xa2, 0x03, 0x69, 0x01, 0xca, 0xd0, 0xfb, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 3);
+        assert_eq!(cpu.register_x, 0);
+    }
+
+    // Register transfers
+
+    #[test]
+    fn test_0xa8_tay() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x80, 0xa8, 0x00]).unwrap();
+        assert_eq!(cpu.register_y, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x8a_txa() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xa2, 0x00, 0x8a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_0x98_tya() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x42, 0x98, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_0xba_tsx() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x48, 0xba, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0xFC);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0x9a_txs() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x00, 0x9a, 0x00]).unwrap();
+        assert_eq!(cpu.stack_pointer, 0x00);
+    }
+
+    #[test]
+    fn test_0x9a_txs_does_not_affect_flags() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // LDX #$80 sets N; LDA #$01 clears it; TXS must not set it again
+        cpu.load_and_run(vec![0xa2, 0x80, 0xa9, 0x01, 0x9a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.stack_pointer, 0x80);
+        assert!(!cpu.is_flag_set(StatusFlag::Negative));
+        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    // Flag manipulation
+
+    #[test]
+    fn test_reset_status() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.status = 0xFF;
+        cpu.load_and_run(vec![0x00]).unwrap();
+        assert_eq!(cpu.status, 0b0010_0100);
+        assert!(cpu.is_flag_set(StatusFlag::InterruptDisable));
+        assert!(cpu.is_flag_set(StatusFlag::Unused));
+    }
+
+    #[test]
+    fn test_0x38_sec() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x38, 0x00]).unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x18_clc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x38, 0x18, 0x00]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    #[test]
+    fn test_0x78_sei() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x58, 0x78, 0x00]).unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::InterruptDisable));
+    }
+
+    #[test]
+    fn test_0x58_cli() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x58, 0x00]).unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::InterruptDisable));
+    }
+
+    #[test]
+    fn test_0xb8_clv() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x50, 0x69, 0x50, 0xb8, 0x00])
+            .unwrap();
+        assert!(!cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_0xea_nop() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x42, 0xea, 0xea, 0xa2, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.register_x, 0x01);
+        assert_eq!(cpu.status, 0b0010_0100);
+    }
+
+    #[test]
+    fn test_sec_sbc() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x10, 0xe9, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0F);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    }
+
+    // Interrupts
+
+    #[test]
+    fn test_0x00_brk_halts_by_default() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x01, 0x00, 0xa9, 0x02])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x01);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert_eq!(cpu.program_counter, 0x8003);
+    }
+
+    #[test]
+    fn test_0x00_brk() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.halt_on_brk = false;
+        cpu.stop_on_self_jump = true;
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: LDX #$42; JMP * (trap)
+        cpu.mem_write(0x9000, 0xa2);
+        cpu.mem_write(0x9001, 0x42);
+        cpu.mem_write(0x9002, 0x4c);
+        cpu.mem_write_u16(0x9003, 0x9002);
+
+        // CLI; BRK
+        cpu.load_and_run(vec![0x58, 0x00]).unwrap();
+        assert_eq!(cpu.register_x, 0x42);
+        assert_eq!(cpu.program_counter, 0x9002);
+        assert_eq!(cpu.stack_pointer, 0xFA);
+        // Return address skips the padding byte after BRK
+        assert_eq!(cpu.mem_read(0x01FD), 0x80);
+        assert_eq!(cpu.mem_read(0x01FC), 0x03);
+        // Pushed status has B and the unused bit set
+        assert_eq!(cpu.mem_read(0x01FB), 0b0011_0000);
+        assert!(cpu.is_flag_set(StatusFlag::InterruptDisable));
+        assert!(!cpu.is_flag_set(StatusFlag::Break));
+    }
+
+    #[test]
+    fn test_0x40_rti() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.halt_on_brk = false;
+        cpu.stop_on_self_jump = true;
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: INX; RTI
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.mem_write(0x9001, 0x40);
+
+        // CLI; SEC; BRK; padding; INY; JMP * (trap)
+        cpu.load_and_run(vec![0x58, 0x38, 0x00, 0xff, 0xc8, 0x4c, 0x05, 0x80])
+            .unwrap();
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.register_y, 1);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
+        assert!(!cpu.is_flag_set(StatusFlag::InterruptDisable));
+        assert!(!cpu.is_flag_set(StatusFlag::Break));
+        assert!(cpu.is_flag_set(StatusFlag::Unused));
+    }
+
+    #[test]
+    fn test_jump_to_self_halts() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.stop_on_self_jump = true;
+        cpu.load_and_run(vec![0xa9, 0x42, 0x4c, 0x02, 0x80])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.program_counter, 0x8002);
+    }
+
+    #[test]
+    fn test_jump_to_self_keeps_running_by_default() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // JMP *
+        cpu.load(vec![0x4c, 0x00, 0x80]);
+        cpu.reset();
+
+        let mut calls = 0;
+        cpu.run_with_callback(|cpu| {
+            calls += 1;
+            // Replace the loop with BRK
+            if calls == 10 {
+                cpu.mem_write(0x8000, 0x00);
+            }
+        })
+        .unwrap();
+        assert_eq!(calls, 10);
+    }
+
+    #[test]
+    fn test_branch_to_self_halts() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.stop_on_self_jump = true;
+        cpu.load_and_run(vec![0xa9, 0x00, 0xf0, 0xfe]).unwrap();
+        assert_eq!(cpu.program_counter, 0x8002);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: LDX #$42; RTI
+        cpu.mem_write(0x9000, 0xa2);
+        cpu.mem_write(0x9001, 0x42);
+        cpu.mem_write(0x9002, 0x40);
+
+        // CLI; NOP; BRK
+        cpu.load(vec![0x58, 0xea, 0x00]);
+        cpu.reset();
+        cpu.set_irq(true);
+        cpu.run_with_callback(|cpu| {
+            // The device releases the line once the handler starts
+            if cpu.program_counter == 0x9000 {
+                cpu.set_irq(false);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(cpu.register_x, 0x42);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert_eq!(cpu.program_counter, 0x8003);
+        // Return address is the next instruction and B is clear on the stack
+        assert_eq!(cpu.mem_read(0x01FD), 0x80);
+        assert_eq!(cpu.mem_read(0x01FC), 0x01);
+        assert_eq!(cpu.mem_read(0x01FB), 0b0010_0000);
+    }
+
+    #[test]
+    fn test_irq_masked_by_interrupt_disable() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.mem_write(0x9001, 0x40);
+
+        cpu.load(vec![0xa9, 0x01, 0x00]);
+        cpu.reset();
+        cpu.set_irq(true);
+        cpu.run().unwrap();
+
+        assert_eq!(cpu.register_x, 0);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn test_irq_is_level_triggered() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: INX; RTI
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.mem_write(0x9001, 0x40);
+
+        // CLI; NOP; BRK
+        cpu.load(vec![0x58, 0xea, 0x00]);
+        cpu.reset();
+        cpu.set_irq(true);
+        cpu.run_with_callback(|cpu| {
+            // Hold the line through three handler runs
+            if cpu.register_x == 3 {
+                cpu.set_irq(false);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(cpu.register_x, 3);
+    }
+
+    #[test]
+    fn test_nmi() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        // Handler: INX; RTI
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.mem_write(0x9001, 0x40);
+
+        // NOP; NOP; BRK with interrupts disabled
+        cpu.load(vec![0xea, 0xea, 0x00]);
+        cpu.reset();
+        cpu.set_nmi(true);
+        cpu.run().unwrap();
+
+        // Serviced exactly once even though the line is still held
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+        assert!(cpu.is_flag_set(StatusFlag::InterruptDisable));
+    }
+
+    #[test]
+    fn test_nmi_is_edge_triggered() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.mem_write(0x9001, 0x40);
+
+        // LDY #$05; DEY; BNE -3; BRK
+        cpu.load(vec![0xa0, 0x05, 0x88, 0xd0, 0xfd, 0x00]);
+        cpu.reset();
+        cpu.run_with_callback(|cpu| match cpu.register_y {
+            4 => cpu.set_nmi(true),
+            3 => cpu.set_nmi(false),
+            2 => cpu.set_nmi(true),
+            _ => {}
+        })
+        .unwrap();
+
+        assert_eq!(cpu.register_x, 2);
+    }
+
+    #[test]
+    fn test_nmi_has_priority_over_irq() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write_u16(0xFFFE, 0x9100);
+        // NMI handler: LDX #$01; BRK. IRQ handler: LDX #$02; BRK
+        cpu.mem_write(0x9000, 0xa2);
+        cpu.mem_write(0x9001, 0x01);
+        cpu.mem_write(0x9100, 0xa2);
+        cpu.mem_write(0x9101, 0x02);
+
+        cpu.load(vec![0x58, 0xea, 0x00]);
+        cpu.reset();
+        cpu.run_with_callback(|cpu| {
+            if cpu.program_counter == 0x8001 {
+                cpu.set_irq(true);
+                cpu.set_nmi(true);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(cpu.register_x, 0x01);
+    }
+
+    #[test]
+    fn test_interrupt_breaks_out_of_trap() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        // Handler: LDX #$42; BRK
+        cpu.mem_write(0x9000, 0xa2);
+        cpu.mem_write(0x9001, 0x42);
+
+        // JMP * waiting for the interrupt
+        cpu.load(vec![0x4c, 0x00, 0x80]);
+        cpu.reset();
+        cpu.run_with_callback(|cpu| cpu.set_nmi(true)).unwrap();
+
+        assert_eq!(cpu.register_x, 0x42);
+    }
+
+    #[test]
+    fn test_interrupt_after_idling() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        // Handler: LDX #$42; BRK
+        cpu.mem_write(0x9000, 0xa2);
+        cpu.mem_write(0x9001, 0x42);
+
+        // JMP * until a timer raises NMI on its tenth call
+        cpu.load(vec![0x4c, 0x00, 0x80]);
+        cpu.reset();
+        let mut calls = 0;
+        cpu.run_with_callback(|cpu| {
+            calls += 1;
+            if calls == 10 {
+                cpu.set_nmi(true);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(cpu.register_x, 0x42);
+        // Ten loops, then the handler's two instructions
+        assert_eq!(calls, 12);
+    }
+
+    #[test]
+    fn test_wdc_irq_after_wai() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: LDY #$42; BRK
+        cpu.mem_write(0x9000, 0xa0);
+        cpu.mem_write(0x9001, 0x42);
+
+        // CLI; WAI; INX; BRK until a timer raises IRQ after ten cycles
+        cpu.load(vec![0x58, 0xcb, 0xe8, 0x00]);
+        cpu.reset();
+        let start = cpu.cycles;
+        cpu.run_with_callback(|cpu| {
+            if cpu.cycles - start >= 10 {
+                cpu.set_irq(true);
+            }
+        })
+        .unwrap();
+
+        // The handler runs before the instruction after WAI
+        assert_eq!(cpu.register_y, 0x42);
+        assert_eq!(cpu.register_x, 0);
+    }
+
+    #[test]
+    fn test_wdc_wai_stops_on_trap() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.stop_on_self_jump = true;
+        // WAI; INX
+        cpu.load_and_run(vec![0xcb, 0xe8]).unwrap();
+        assert_eq!(cpu.program_counter, 0x8001);
+        assert_eq!(cpu.register_x, 0);
+    }
+
+    // Bus
+
+    // 2 KiB of RAM mirrored up to $5FFF, 32 KiB of ROM in the upper half and an
+    // output port at $6000 that records everything written to it
+    struct TestBus {
+        ram: [u8; 0x0800],
+        rom: Vec<u8>,
+        port: Vec<u8>,
+        fault: Option<u16>,
+    }
+
+    impl Bus for TestBus {
+        fn read(&mut self, addr: u16) -> u8 {
+            match addr {
+                0x0000..=0x5FFF => self.ram[(addr & 0x07FF) as usize],
+                0x8000..=0xFFFF => self.rom[(addr - 0x8000) as usize],
+                _ => {
+                    self.fault = Some(addr);
+                    0
+                }
+            }
+        }
+
+        fn write(&mut self, addr: u16, data: u8) {
+            match addr {
+                0x0000..=0x5FFF => self.ram[(addr & 0x07FF) as usize] = data,
+                0x6000 => self.port.push(data),
+                _ => {}
+            }
+        }
+
+        fn take_fault(&mut self) -> Option<u16> {
+            self.fault.take()
+        }
+    }
+
+    #[test]
+    fn test_custom_bus() {
+        let mut rom = vec![0; 0x8000];
+        // LDA #$42; STA $6000; STA $0810; LDX $0010; STA $9000; BRK
+        let program = [
+            0xa9, 0x42, 0x8d, 0x00, 0x60, 0x8d, 0x10, 0x08, 0xae, 0x10, 0x00, 0x8d, 0x00, 0x90,
             0x00,
-        ]);
-        assert_eq!(cpu.register_a, 0b1111_0111);
-        assert_eq!(
-            cpu.status & (StatusFlag::Negative as u8),
-            StatusFlag::Negative as u8
+        ];
+        rom[..program.len()].copy_from_slice(&program);
+        rom[0x7FFC] = 0x00;
+        rom[0x7FFD] = 0x80;
+
+        let mut cpu = CPU::with_bus(
+            TestBus {
+                ram: [0; 0x0800],
+                rom,
+                port: vec![],
+                fault: None,
+            },
+            Variant::Nmos6502,
         );
-        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
+        cpu.reset();
+        cpu.run().unwrap();
+
+        assert_eq!(cpu.bus.port, vec![0x42]);
+        // $0810 mirrors $0010
+        assert_eq!(cpu.register_x, 0x42);
+        // ROM ignores writes
+        assert_eq!(cpu.mem_read(0x9000), 0x00);
+        assert_eq!(cpu.bus.peek(0x0010), None);
     }
 
-    // Zero Page X
     #[test]
-    fn test_0x35_and() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![
-            0xa9,
-            0xFF,
-            0x85,
-            0x10,
-            0xa9,
-            0b1111_0111,
-            0x35,
-            0x10,
-            0x00,
-        ]);
-        assert_eq!(cpu.register_a, 0b1111_0111);
+    fn test_bus_fault() {
+        let mut rom = vec![0; 0x8000];
+        // LDA $0010; LDA $7000; BRK
+        rom[..7].copy_from_slice(&[0xa5, 0x10, 0xad, 0x00, 0x70, 0x00, 0x00]);
+        rom[0x7FFC] = 0x00;
+        rom[0x7FFD] = 0x80;
+
+        let mut cpu = CPU::with_bus(
+            TestBus {
+                ram: [0; 0x0800],
+                rom,
+                port: vec![],
+                fault: None,
+            },
+            Variant::Nmos6502,
+        );
+        cpu.reset();
         assert_eq!(
-            cpu.status & (StatusFlag::Negative as u8),
-            StatusFlag::Negative as u8
+            cpu.run(),
+            Err(CpuError::BusFault {
+                address: 0x7000,
+                pc: 0x8002
+            })
         );
-        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
     }
 
-    // Absolute
+    // Address space boundaries
+
     #[test]
-    fn test_0x2d_and() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0b1111_1111);
-        cpu.load_and_run(vec![0xa9, 0b1010_1010, 0x2d, 0x34, 0x12, 0x00]);
-        assert_eq!(cpu.register_a, 0b1010_1010);
+    fn test_mem_read_u16_wraps_at_top_of_memory() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0xFFFF, 0x34);
+        cpu.mem_write(0x0000, 0x12);
+        assert_eq!(cpu.mem_read_u16(0xFFFF), 0x1234);
     }
 
-    // Absolute X
     #[test]
-    fn test_0x3d_and() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x4321, 0b1111_1111);
-        // Load A with 42. Load X with 1. Address 0x4320 + 1 (see above). AND with A (42)
-        cpu.load_and_run(vec![0xa9, 42, 0xa2, 1, 0x3d, 0x20, 0x43, 0x00]);
-        assert_eq!(cpu.register_a, 42);
+    fn test_mem_write_u16_wraps_at_top_of_memory() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFF, 0x1234);
+        assert_eq!(cpu.mem_read(0xFFFF), 0x34);
+        assert_eq!(cpu.mem_read(0x0000), 0x12);
     }
 
-    // Absoulute Y
     #[test]
-    fn test_0x39_and() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x4321, 0b0000_1000);
-        // Load A with bits. Load Y with 3. Address 0x431E + Y (see above). AND that with A's bits
-        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa0, 3, 0x39, 0x1E, 0x43, 0x00]);
-        assert_eq!(cpu.register_a, 8);
+    fn test_mem_read_u16_zero_page_wraps() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x00FF, 0x34);
+        cpu.mem_write(0x0000, 0x12);
+        cpu.mem_write(0x0100, 0x56);
+        assert_eq!(cpu.mem_read_u16_zero_page(0xFF), 0x1234);
     }
 
-    // Indexed Indirect (Indirect X)
     #[test]
-    fn test_0x21_and() {
-        let mut cpu = CPU::new();
-        cpu.mem_write_u16(0x14, 0x1234);
-        cpu.mem_write(0x1234, 0b0010_0010);
-        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa2, 4, 0x21, 0x10, 0x00]);
-        assert_eq!(cpu.register_a, 2);
+    fn test_vectors() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x1111);
+        cpu.mem_write_u16(0xFFFC, 0x2222);
+        cpu.mem_write_u16(0xFFFE, 0x3333);
+        assert_eq!(cpu.mem_read(0xFFFF), 0x33);
+        assert_eq!(cpu.mem_read_u16(NMI_VECTOR), 0x1111);
+        assert_eq!(cpu.mem_read_u16(IRQ_BRK_VECTOR), 0x3333);
+        cpu.reset();
+        assert_eq!(cpu.program_counter, 0x2222);
     }
 
-    // Indirect Indexed (Indirect Y)
     #[test]
-    fn test_0x31_and() {
-        let mut cpu = CPU::new();
-        cpu.mem_write_u16(0x10, 0x1234);
-        cpu.mem_write(0x1238, 0b0010_0010);
-        cpu.load_and_run(vec![0xa9, 0b0000_1111, 0xa0, 4, 0x31, 0x10, 0x00]);
-        assert_eq!(cpu.register_a, 2);
+    fn test_0xa1_lda_pointer_wraps_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x00FF, 0x34);
+        cpu.mem_write(0x0000, 0x12);
+        cpu.mem_write(0x1234, 0x55);
+        // ($FE,X) with X = 1 reads its pointer from $FF and $00
+        cpu.load_and_run(vec![0xa2, 0x01, 0xa1, 0xFE, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
     }
 
     #[test]
-    fn test_0x0a_asl() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 2, 0x0a, 0x0a, 0x00]);
-        assert_eq!(cpu.register_a, 8);
-        assert_eq!(cpu.status & (StatusFlag::Zero as u8), 0);
-        assert_eq!(cpu.status & (StatusFlag::Negative as u8), 0);
-        assert_eq!(cpu.status & (StatusFlag::Carry as u8), 0);
+    fn test_0xb1_lda_pointer_wraps_zero_page() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x00FF, 0x30);
+        cpu.mem_write(0x0000, 0x12);
+        cpu.mem_write(0x1234, 0x55);
+        cpu.load_and_run(vec![0xa0, 0x04, 0xb1, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
     }
 
     #[test]
-    fn test_0x0a_asl_overflow() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0x00]);
-        assert_eq!(cpu.register_a, 0b1111_1110);
-        assert!(!cpu.is_flag_set(StatusFlag::Zero));
-        assert!(cpu.is_flag_set(StatusFlag::Negative));
-        assert!(cpu.is_flag_set(StatusFlag::Carry));
+    fn test_0xbd_lda_wraps_address_space() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write(0x0001, 0x55);
+        cpu.load_and_run(vec![0xa2, 0x02, 0xbd, 0xFF, 0xFF, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x55);
     }
 
     #[test]
-    fn test_0x0e_asl() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0b0111_0011);
-        cpu.load_and_run(vec![0x0e, 0x34, 0x12, 0x00]);
+    fn test_program_counter_wraps() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // LDA #$42 straddling $FFFF and $0000, then BRK at $0001
+        cpu.mem_write(0xFFFF, 0xa9);
+        cpu.mem_write(0x0000, 0x42);
+        cpu.mem_write_u16(0xFFFC, 0xFFFF);
+        cpu.reset();
+        cpu.run().unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.program_counter, 0x0002);
+    }
 
-        let result = cpu.mem_read(0x1234);
-        assert_eq!(result, 0b1110_0110);
-        assert!(cpu.is_flag_set(StatusFlag::Negative));
-        assert!(!cpu.is_flag_set(StatusFlag::Carry));
-        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    // Cycle counting
+
+    #[test]
+    fn test_reset_takes_seven_cycles() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x00]).unwrap();
+        assert_eq!(cpu.cycles, 7);
     }
-    // Other ASL: 0x06, 0x16, 0x1E
 
     #[test]
-    fn test_0x90_bcc() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0x90, 3, 0xa9, 123, 0x00]);
-        assert_eq!(cpu.register_a, 0);
+    fn test_cycles_base() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // LDA #$01 (2), STA $10 (3), INC $10 (5), JMP abs (3), BRK halts
+        cpu.load_and_run(vec![
+            0xa9, 0x01, 0x85, 0x10, 0xe6, 0x10, 0x4c, 0x09, 0x80, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 3 + 5 + 3);
     }
 
     #[test]
-    fn test_0x90_bcc_no_branch() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0x90, 3, 0xa9, 123, 0x00]);
-        assert_eq!(cpu.register_a, 123);
+    fn test_cycles_absolute_x_read() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x01, 0xbd, 0x00, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 4);
     }
 
     #[test]
-    fn test_0xb0_bcs() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xFF, 0x0a, 0xb0, 2, 0xa9, 123, 0x00]);
-        assert_eq!(cpu.register_a, 0xFF << 1);
+    fn test_cycles_absolute_x_read_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x01, 0xbd, 0xFF, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 5);
     }
 
     #[test]
-    fn test_0xb0_bcs_no_branch() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xb0, 3, 0xa9, 123, 0x00]);
-        assert_eq!(cpu.register_a, 123);
+    fn test_cycles_absolute_y_read_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa0, 0x02, 0x79, 0xFF, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 5);
     }
 
     #[test]
-    fn test_0xf0_beq() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0, 0xf0, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00]);
-        assert_eq!(cpu.register_a, 0);
-        assert_eq!(cpu.register_x, 0x15);
+    fn test_cycles_indirect_y_read_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0x10, 0x12FF);
+        cpu.load_and_run(vec![0xa0, 0x01, 0xb1, 0x10, 0xa0, 0x00, 0xb1, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 6 + 2 + 5);
     }
 
     #[test]
-    fn test_0xd0_bne() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 1, 0xd0, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00]);
-        assert_eq!(cpu.register_a, 1);
-        assert_eq!(cpu.register_x, 0x15);
+    fn test_cycles_store_has_no_page_penalty() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x01, 0x9d, 0xFF, 0x12, 0x9d, 0x00, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 5 + 5);
     }
 
     #[test]
-    fn test_0x10_bpl() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 1, 0x10, 3, 0xa9, 0xff, 69, 0xa2, 0x15, 0x00]);
-        assert_eq!(cpu.register_a, 1);
-        assert_eq!(cpu.register_x, 0x15);
+    fn test_cycles_read_modify_write_has_no_page_penalty() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa2, 0x01, 0x1e, 0xFF, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 7);
     }
 
     #[test]
-    fn test_0x50_bvc() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 69, 0x50, 3, 0xa9, 96, 0x00]);
-        assert_eq!(cpu.register_a, 69);
+    fn test_cycles_branch_not_taken() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x01, 0xf0, 0x10, 0x00])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 2 + 2);
     }
 
     #[test]
-    fn test_0x70_bvs() {
-        let mut cpu = CPU::new();
-        cpu.mem_write_u16(0x1234, 0xff);
-        cpu.load_and_run(vec![0xa9, 69, 0x2c, 0x34, 0x12, 0x70, 3, 0xa9, 96, 0x00]);
-        assert_eq!(cpu.register_a, 69);
+    fn test_cycles_branch_taken() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0xa9, 0x00, 0xf0, 0x7F, 0x00])
+            .unwrap();
+        assert_eq!(cpu.program_counter, 0x8084);
+        assert_eq!(cpu.cycles, 7 + 2 + 3);
     }
 
     #[test]
-    fn test_0x2c_bit() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0xff);
-        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]);
-        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    fn test_cycles_branch_taken_page_crossed() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // Branches back into $7Fxx, which holds a BRK
+        cpu.load_and_run(vec![0xa9, 0x00, 0xf0, 0xF0, 0x00])
+            .unwrap();
+        assert_eq!(cpu.program_counter, 0x7FF5);
+        assert_eq!(cpu.cycles, 7 + 2 + 4);
+    }
+
+    #[test]
+    fn test_cycles_subroutine() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x20, 0x04, 0x80, 0x00, 0x60])
+            .unwrap();
+        assert_eq!(cpu.cycles, 7 + 6 + 6);
+    }
+
+    #[test]
+    fn test_cycles_interrupt() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write(0x9000, 0x40);
+
+        cpu.load(vec![0xea, 0x00]);
+        cpu.reset();
+        cpu.set_nmi(true);
+        cpu.run().unwrap();
+
+        // Reset, NMI sequence, RTI, NOP
+        assert_eq!(cpu.cycles, 7 + 7 + 6 + 2);
+    }
+
+    #[test]
+    fn test_cycles_brk() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.halt_on_brk = false;
+        cpu.stop_on_self_jump = true;
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        cpu.mem_write(0x9000, 0x4c);
+        cpu.mem_write_u16(0x9001, 0x9000);
+
+        cpu.load_and_run(vec![0x00]).unwrap();
+        assert_eq!(cpu.cycles, 7 + 7 + 3);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0xa2, 0x20, 0xbd, 0xF0, 0x12, 0x00]);
+        cpu.reset();
+
+        let step = cpu.step().unwrap();
+        assert_eq!(step.address, 0x8000);
+        assert_eq!(step.mnemonic, "LDX");
+        assert_eq!(step.mode, AddressingMode::Immediate);
+        assert_eq!(step.effective_address, None);
+        assert_eq!(step.cycles, 2);
+
+        let step = cpu.step().unwrap();
+        assert_eq!(step.opcode, 0xbd);
+        assert_eq!(step.mode, AddressingMode::AbsoluteX);
+        assert_eq!(step.effective_address, Some(0x1310));
+        assert_eq!(step.cycles, 5);
+        assert!(!step.halted);
+        assert_eq!(cpu.program_counter, 0x8005);
+
+        assert!(cpu.step().unwrap().halted);
+    }
+
+    #[test]
+    fn test_step_branch() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0xa9, 0x00, 0xd0, 0x02, 0xf0, 0x02]);
+        cpu.reset();
+        cpu.step().unwrap();
+
+        let step = cpu.step().unwrap();
+        assert!(!step.branch_taken);
+        assert_eq!(step.cycles, 2);
+
+        let step = cpu.step().unwrap();
+        assert!(step.branch_taken);
+        assert_eq!(step.cycles, 3);
+        assert_eq!(cpu.program_counter, 0x8008);
+    }
+
+    #[test]
+    fn test_step_branch_back_one_byte() {
+        // BNE -1 lands on its own operand, one byte past the opcode
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0xd0, 0xff]);
+        cpu.reset();
+
+        let step = cpu.step().unwrap();
+        assert!(step.branch_taken);
+        assert_eq!(cpu.program_counter, 0x8001);
+    }
+
+    #[test]
+    fn test_step_services_interrupt() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write(0x9000, 0xe8);
+        cpu.load(vec![0xea]);
+        cpu.reset();
+        cpu.set_nmi(true);
+
+        let step = cpu.step().unwrap();
+        assert_eq!(step.address, 0x9000);
+        assert_eq!(step.mnemonic, "INX");
+        assert_eq!(step.cycles, 7 + 2);
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // INX; JMP $8000
+        cpu.load(vec![0xe8, 0x4c, 0x00, 0x80]);
+        cpu.reset();
+
+        assert_eq!(cpu.run_for(10).unwrap(), 10);
+        assert_eq!(cpu.register_x, 2);
+
+        // Overshoots to finish the INX
+        assert_eq!(cpu.run_for(1).unwrap(), 2);
+        assert_eq!(cpu.register_x, 3);
+    }
+
+    #[test]
+    fn test_run_for_stops_on_brk() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load(vec![0xe8, 0x00]);
+        cpu.reset();
+        assert_eq!(cpu.run_for(100).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_run_until() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // INX; JMP $8000
+        cpu.load(vec![0xe8, 0x4c, 0x00, 0x80]);
+        cpu.reset();
+
+        let step = cpu.run_until(|cpu| cpu.register_x == 5).unwrap();
+        assert_eq!(step.mnemonic, "INX");
+        assert_eq!(cpu.register_x, 5);
+        assert_eq!(cpu.program_counter, 0x8001);
+    }
+
+    #[test]
+    fn test_run_until_waits_out_a_jump_to_self() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        // JMP * while a scheduler waits for 30 cycles to pass
+        cpu.load(vec![0x4c, 0x00, 0x80]);
+        cpu.reset();
+        let start = cpu.cycles;
+
+        let step = cpu.run_until(|cpu| cpu.cycles - start >= 30).unwrap();
+        assert_eq!(step.mnemonic, "JMP");
+        assert_eq!(cpu.cycles - start, 30);
+        assert_eq!(cpu.run_for(30).unwrap(), 30);
+
+        // Only stops early when asked to
+        cpu.stop_on_self_jump = true;
+        assert_eq!(cpu.run_for(30).unwrap(), 3);
+        let step = cpu.run_until(|_| false).unwrap();
+        assert_eq!(step.address, 0x8000);
+    }
+
+    // Variants
+
+    #[test]
+    fn test_2a03_ignores_decimal_mode() {
+        let mut cpu = CPU::new(Variant::Ricoh2A03);
+        // SED; LDA #$09; CLC; ADC #$01; BRK
+        cpu.load_and_run(vec![0xf8, 0xa9, 0x09, 0x18, 0x69, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x0A);
+        assert!(cpu.is_flag_set(StatusFlag::Decimal));
+    }
+
+    #[test]
+    fn test_cmos_jmp_indirect_without_page_bug() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.mem_write(0x30FF, 0x80);
+        cpu.mem_write(0x3000, 0x50);
+        cpu.mem_write(0x3100, 0x90);
+        cpu.mem_write(0x9080, 0x00);
+        cpu.load(vec![0x6c, 0xff, 0x30]);
+        cpu.reset();
+
+        let step = cpu.step().unwrap();
+        assert_eq!(cpu.program_counter, 0x9080);
+        assert_eq!(step.cycles, 6);
+    }
+
+    #[test]
+    fn test_cmos_0x7c_jmp_absolute_indexed_indirect() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // The vector table crosses a page, which the 65C02 handles
+        cpu.mem_write_u16(0x30FE, 0x9000);
+        cpu.mem_write_u16(0x3100, 0x9080);
+        cpu.mem_write(0x9080, 0x00);
+        // LDX #$02; JMP ($30FE,X)
+        cpu.load(vec![0xa2, 0x02, 0x7c, 0xfe, 0x30]);
+        cpu.reset();
+        cpu.step().unwrap();
+
+        let step = cpu.step().unwrap();
+        assert_eq!(cpu.program_counter, 0x9080);
+        assert_eq!(step.cycles, 6);
+        assert_eq!(step.mode, AddressingMode::AbsoluteIndexedIndirect);
+    }
+
+    #[test]
+    fn test_cmos_interrupt_clears_decimal() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.halt_on_brk = false;
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // SED; BRK
+        cpu.load(vec![0xf8, 0x00]);
+        cpu.reset();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert!(!cpu.is_flag_set(StatusFlag::Decimal));
+        // The pushed status still has D set
+        assert_eq!(cpu.mem_read(0x01FB) & 0b0000_1000, 0b0000_1000);
+    }
+
+    #[test]
+    fn test_nmos_interrupt_keeps_decimal() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.halt_on_brk = false;
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        cpu.load(vec![0xf8, 0x00]);
+        cpu.reset();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+
+        assert!(cpu.is_flag_set(StatusFlag::Decimal));
+    }
+
+    #[test]
+    fn test_cmos_0x80_bra() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // BRA +2; LDX #$01; BRK
+        cpu.load_and_run(vec![0x80, 0x02, 0xa2, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0);
+    }
+
+    #[test]
+    fn test_nmos_0x80_is_a_nop() {
+        let mut cpu = CPU::new(Variant::Nmos6502);
+        cpu.load_and_run(vec![0x80, 0x02, 0xa2, 0x01, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_cmos_phx_ply() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // LDX #$80; PHX; PLY; BRK
+        cpu.load_and_run(vec![0xa2, 0x80, 0xda, 0x7a, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_y, 0x80);
+        assert_eq!(cpu.stack_pointer, 0xFD);
         assert!(cpu.is_flag_set(StatusFlag::Negative));
-        assert!(cpu.is_flag_set(StatusFlag::Overflow));
     }
 
     #[test]
-    fn test_0x2c_bit_2() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0xff);
-        cpu.load_and_run(vec![0x2c, 0x34, 0x12]);
+    fn test_cmos_phy_plx() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // LDY #$00; PHY; LDX #$05; PLX; BRK
+        cpu.load_and_run(vec![0xa0, 0x00, 0x5a, 0xa2, 0x05, 0xfa, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_x, 0x00);
         assert!(cpu.is_flag_set(StatusFlag::Zero));
-        assert!(cpu.is_flag_set(StatusFlag::Negative));
-        assert!(cpu.is_flag_set(StatusFlag::Overflow));
     }
 
     #[test]
-    fn test_0x2c_bit_3() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0b_0011_1111);
-        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]);
-        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    fn test_cmos_stz() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.mem_write(0x10, 0xFF);
+        cpu.mem_write(0x1234, 0xFF);
+        // STZ $10; STZ $1234; BRK
+        cpu.load_and_run(vec![0x64, 0x10, 0x9c, 0x34, 0x12, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0x00);
+        assert_eq!(cpu.mem_read(0x1234), 0x00);
+    }
+
+    #[test]
+    fn test_cmos_trb_tsb() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.mem_write(0x10, 0b1010_1010);
+        cpu.mem_write(0x11, 0b0101_0000);
+        // LDA #$0F; TSB $10; TRB $11; BRK
+        cpu.load_and_run(vec![0xa9, 0x0f, 0x04, 0x10, 0x14, 0x11, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b1010_1111);
+        assert_eq!(cpu.mem_read(0x11), 0b0101_0000);
+        // No bits of $11 were in A
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_cmos_zero_page_indirect() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.mem_write_u16(0x20, 0x1234);
+        cpu.mem_write(0x1234, 0x42);
+        // LDA ($20); STA ($22) after pointing $22 at $0300
+        cpu.mem_write_u16(0x22, 0x0300);
+        cpu.load_and_run(vec![0xb2, 0x20, 0x92, 0x22, 0x00])
+            .unwrap();
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.mem_read(0x0300), 0x42);
+    }
+
+    #[test]
+    fn test_cmos_bit_immediate_only_sets_zero() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // LDA #$01; BIT #$C0; BRK
+        cpu.load_and_run(vec![0xa9, 0x01, 0x89, 0xc0, 0x00])
+            .unwrap();
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
         assert!(!cpu.is_flag_set(StatusFlag::Negative));
         assert!(!cpu.is_flag_set(StatusFlag::Overflow));
     }
 
     #[test]
-    fn test_0x2c_bit_4() {
-        let mut cpu = CPU::new();
-        cpu.mem_write(0x1234, 0b_0111_1111);
-        cpu.load_and_run(vec![0xa9, 1, 0x2c, 0x34, 0x12]);
-        assert!(!cpu.is_flag_set(StatusFlag::Zero));
+    fn test_cmos_decimal_flags_and_timing() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        // SED; LDA #$99; CLC; ADC #$01
+        cpu.load(vec![0xf8, 0xa9, 0x99, 0x18, 0x69, 0x01]);
+        cpu.reset();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+
+        let step = cpu.step().unwrap();
+        assert_eq!(cpu.register_a, 0x00);
+        assert_eq!(step.cycles, 3);
+        // NMOS parts leave Z clear here because the binary result is $9A
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
         assert!(!cpu.is_flag_set(StatusFlag::Negative));
-        assert!(cpu.is_flag_set(StatusFlag::Overflow));
+        assert!(cpu.is_flag_set(StatusFlag::Carry));
     }
-    // Other BIT: 0x24
 
     #[test]
-    fn test_0x30_bmi() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa2, 0xff, 0x30, 3, 0xa2, 69, 0x00]);
-        assert_eq!(cpu.register_x, 0xff);
+    fn test_wdc_rmb_smb() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.mem_write(0x10, 0b1111_1111);
+        // RMB3 $10; SMB0 $11; BRK
+        cpu.load_and_run(vec![0x37, 0x10, 0x87, 0x11, 0x00])
+            .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b1111_0111);
+        assert_eq!(cpu.mem_read(0x11), 0b0000_0001);
     }
 
     #[test]
-    fn test_0xa2_ldx() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa2, 0xee, 0x00]);
-        assert_eq!(cpu.register_x, 0xee);
+    fn test_cmos_rmb_is_a_nop() {
+        let mut cpu = CPU::new(Variant::Cmos65C02);
+        cpu.mem_write(0x10, 0b1111_1111);
+        cpu.load_and_run(vec![0x37, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.mem_read(0x10), 0b1111_1111);
     }
 
     #[test]
-    fn test_0xa0_ldy() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa0, 0x12, 0x00]);
-        assert_eq!(cpu.register_y, 0x12);
+    fn test_wdc_bbr_bbs() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.mem_write(0x10, 0b0000_0100);
+        // BBS2 $10,+2; LDX #$01; BBR2 $10,+2; LDY #$01; BRK
+        cpu.load_and_run(vec![
+            0xaf, 0x10, 0x02, 0xa2, 0x01, 0x2f, 0x10, 0x02, 0xa0, 0x01, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(cpu.register_x, 0x00);
+        assert_eq!(cpu.register_y, 0x01);
     }
 
     #[test]
-    fn test_0x85_sta() {
-        let mut cpu = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xFF, 0x85, 0x10, 0x00]);
-        assert_eq!(cpu.mem_read(0x10 as u16), 0xFF);
+    fn test_wdc_bbr_timing() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        // BBR0 $10,+0
+        cpu.load(vec![0x0f, 0x10, 0x00]);
+        cpu.reset();
+        let step = cpu.step().unwrap();
+        assert!(step.branch_taken);
+        assert_eq!(step.cycles, 6);
+        assert_eq!(cpu.program_counter, 0x8003);
+    }
+
+    #[test]
+    fn test_wdc_stp() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.load(vec![0xe8, 0xdb, 0xe8]);
+        cpu.reset();
+        assert_eq!(cpu.run(), Err(CpuError::Stopped { pc: 0x8001 }));
+        assert_eq!(cpu.register_x, 1);
+
+        // Only a reset gets it going again
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step(), Err(CpuError::Stopped { pc: 0x8001 }));
+        cpu.reset();
+        assert_eq!(cpu.step().unwrap().mnemonic, "INX");
+    }
+
+    #[test]
+    fn test_wdc_wai_wakes_on_irq() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // Handler: LDY #$42; RTI
+        cpu.mem_write(0x9000, 0xa0);
+        cpu.mem_write(0x9001, 0x42);
+        cpu.mem_write(0x9002, 0x40);
+
+        // CLI; WAI; INX; BRK
+        cpu.load(vec![0x58, 0xcb, 0xe8, 0x00]);
+        cpu.reset();
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap().mnemonic, "WAI");
+
+        // Idles one cycle at a time until the line goes low
+        assert_eq!(cpu.run_for(10).unwrap(), 10);
+        assert_eq!(cpu.register_x, 0);
+
+        cpu.set_irq(true);
+        let step = cpu.step().unwrap();
+        cpu.set_irq(false);
+        assert_eq!(step.mnemonic, "LDY");
+        cpu.run().unwrap();
+        assert_eq!(cpu.register_y, 0x42);
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_wdc_wai_with_interrupts_masked() {
+        let mut cpu = CPU::new(Variant::Wdc65C02);
+        // WAI; INX; BRK
+        cpu.load(vec![0xcb, 0xe8, 0x00]);
+        cpu.reset();
+        cpu.step().unwrap();
+
+        // I is set, so the IRQ only resumes execution after WAI
+        cpu.set_irq(true);
+        cpu.run().unwrap();
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.stack_pointer, 0xFD);
     }
 }
@@ -0,0 +1,75 @@
+// Everything the CPU sees goes through a bus: RAM, ROM, mirrors and memory-mapped I/O.
+// Reads take `&mut self` because reading a device register may have side effects
+// (e.g. acknowledging an interrupt or popping a FIFO).
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+
+    fn write(&mut self, addr: u16, data: u8);
+
+    // Reads without side effects, for debuggers and disassemblers. Buses that cannot
+    // do so return None.
+    fn peek(&self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    // Reports an access the bus could not serve since the last call, such as a write to
+    // ROM or a read of unmapped memory. The CPU checks this after every instruction.
+    fn take_fault(&mut self) -> Option<u16> {
+        None
+    }
+}
+
+// Flat, fully writable 64 KiB of memory. This is the default bus.
+pub struct Ram {
+    memory: Box<[u8]>,
+}
+
+impl Default for Ram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ram {
+    pub fn new() -> Self {
+        Ram {
+            memory: vec![0; 0x10000].into_boxed_slice(),
+        }
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.memory[addr as usize] = data;
+    }
+
+    fn peek(&self, addr: u16) -> Option<u8> {
+        Some(self.memory[addr as usize])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ram_read_write() {
+        let mut ram = Ram::new();
+        ram.write(0x1234, 0x42);
+        assert_eq!(ram.read(0x1234), 0x42);
+        assert_eq!(ram.peek(0x1234), Some(0x42));
+    }
+
+    #[test]
+    fn test_ram_covers_whole_address_space() {
+        let mut ram = Ram::new();
+        ram.write(0x0000, 0x01);
+        ram.write(0xFFFF, 0x02);
+        assert_eq!(ram.read(0x0000), 0x01);
+        assert_eq!(ram.read(0xFFFF), 0x02);
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    // An undocumented opcode, under `IllegalOpcodePolicy::Error`
+    IllegalOpcode { opcode: u8, pc: u16 },
+    // The CPU has locked up, through a JAM opcode or `IllegalOpcodePolicy::Jam`. Only a
+    // reset brings it back.
+    Jammed { pc: u16 },
+    // The WDC 65C02 executed STP. Only a reset brings it back.
+    Stopped { pc: u16 },
+    // The bus reported an access it could not serve
+    BusFault { address: u16, pc: u16 },
+    // `step` or `run` was called while `tick` was part way through an instruction
+    InstructionInProgress { pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::IllegalOpcode { opcode, pc } => {
+                write!(f, "illegal opcode ${:02X} at ${:04X}", opcode, pc)
+            }
+            CpuError::Jammed { pc } => write!(f, "CPU jammed at ${:04X}", pc),
+            CpuError::Stopped { pc } => write!(f, "CPU stopped at ${:04X}", pc),
+            CpuError::BusFault { address, pc } => {
+                write!(
+                    f,
+                    "bus fault at ${:04X} (instruction at ${:04X})",
+                    address, pc
+                )
+            }
+            CpuError::InstructionInProgress { pc } => {
+                write!(f, "instruction at ${:04X} is still in progress", pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+// What to do when the CPU fetches an opcode outside the documented instruction set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IllegalOpcodePolicy {
+    // Run it as the NMOS 6502 does
+    #[default]
+    Execute,
+    // Stop and report `CpuError::IllegalOpcode`, leaving the program counter on the opcode
+    Error,
+    // Treat it as a one byte, two cycle NOP
+    Nop,
+    // Lock up like the KIL/JAM opcodes on real hardware until the next reset
+    Jam,
+}
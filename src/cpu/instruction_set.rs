@@ -4,87 +4,678 @@ use instruction::Instruction;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
-const INSTRUCTION_SET_SIZE: usize = 30;
+// Documented instructions, common to every variant
+const INSTRUCTION_SET_SIZE: usize = 151;
+// Undocumented instructions of the NMOS 6502 and the Ricoh 2A03
+const NMOS_INSTRUCTION_SET_SIZE: usize = 105;
+// Changes and additions of the 65C02
+const CMOS_INSTRUCTION_SET_SIZE: usize = 110;
+// Instructions the WDC 65C02 has on top of the 65C02
+const WDC_INSTRUCTION_SET_SIZE: usize = 34;
 
 lazy_static! {
     pub static ref INSTRUCTION_SET: [Instruction; INSTRUCTION_SET_SIZE] = [
+        // ADC - Add with Carry
+        Instruction::new(0x69, "ADC", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0x65, "ADC", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x75, "ADC", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x6D, "ADC", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x7D, "ADC", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0x79, "ADC", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0x61, "ADC", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0x71, "ADC", AddressingMode::IndirectY, 2, 5),
+
         // AND - Logical AND
-        Instruction::new(0x29, "AND", AddressingMode::Immediate, 2),
-        Instruction::new(0x25, "AND", AddressingMode::ZeroPage, 2),
-        Instruction::new(0x35, "AND", AddressingMode::ZeroPageX, 2),
-        Instruction::new(0x2D, "AND", AddressingMode::Absolute, 3),
-        Instruction::new(0x3D, "AND", AddressingMode::AbsoluteX, 3),
-        Instruction::new(0x39, "AND", AddressingMode::AbsoluteY, 3),
-        Instruction::new(0x21, "AND", AddressingMode::IndirectX, 2),
-        Instruction::new(0x31, "AND", AddressingMode::IndirectY, 2),
+        Instruction::new(0x29, "AND", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0x25, "AND", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x35, "AND", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x2D, "AND", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x3D, "AND", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0x39, "AND", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0x21, "AND", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0x31, "AND", AddressingMode::IndirectY, 2, 5),
 
         // ASL - Arithmetic Shift Left
-        Instruction::new(0x0A, "ASL", AddressingMode::Accumulator, 1),
-        Instruction::new(0x06, "ASL", AddressingMode::ZeroPage, 2),
-        Instruction::new(0x16, "ASL", AddressingMode::ZeroPageX, 2),
-        Instruction::new(0x0E, "ASL", AddressingMode::Absolute, 3),
-        Instruction::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3),
+        Instruction::new(0x0A, "ASL", AddressingMode::Accumulator, 1, 2),
+        Instruction::new(0x06, "ASL", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x16, "ASL", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0x0E, "ASL", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3, 7),
 
         // BCC - Branch if Carry Clear
-        Instruction::new(0x90, "BCC", AddressingMode::Relative, 2),
+        Instruction::new(0x90, "BCC", AddressingMode::Relative, 2, 2),
 
         // BCS - Branch if Carry Set
-        Instruction::new(0xB0, "BCS", AddressingMode::Relative, 2),
+        Instruction::new(0xB0, "BCS", AddressingMode::Relative, 2, 2),
 
         // BEQ - Branch if Equal
-        Instruction::new(0xF0, "BEQ", AddressingMode::Relative, 2),
+        Instruction::new(0xF0, "BEQ", AddressingMode::Relative, 2, 2),
 
         // BRK - (Break) Force Interrupt
-        Instruction::new(0x00, "BRK", AddressingMode::Implicit, 1),
+        Instruction::new(0x00, "BRK", AddressingMode::Implicit, 1, 7),
 
         // BIT - Bit Test
-        Instruction::new(0x24, "BIT", AddressingMode::ZeroPage, 2),
-        Instruction::new(0x2C, "BIT", AddressingMode::Absolute, 3),
+        Instruction::new(0x24, "BIT", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x2C, "BIT", AddressingMode::Absolute, 3, 4),
 
         // BMI - Branch if Minus
-        Instruction::new(0x30, "BMI", AddressingMode::Relative, 2),
+        Instruction::new(0x30, "BMI", AddressingMode::Relative, 2, 2),
 
         // BNE - Branch if Not Equal
-        Instruction::new(0xD0, "BNE", AddressingMode::Relative, 2),
+        Instruction::new(0xD0, "BNE", AddressingMode::Relative, 2, 2),
 
         // BPL - Branch if Positive
-        Instruction::new(0x10, "BPL", AddressingMode::Relative, 2),
+        Instruction::new(0x10, "BPL", AddressingMode::Relative, 2, 2),
 
         // BVC - Branch if Overflow Clear
-        Instruction::new(0x50, "BVC", AddressingMode::Relative, 2),
+        Instruction::new(0x50, "BVC", AddressingMode::Relative, 2, 2),
 
         // BVS - Branch if Overflow Set
-        Instruction::new(0x70, "BVS", AddressingMode::Relative, 2),
+        Instruction::new(0x70, "BVS", AddressingMode::Relative, 2, 2),
+
+        // CLC - Clear Carry Flag
+        Instruction::new(0x18, "CLC", AddressingMode::Implicit, 1, 2),
+
+        // CLD - Clear Decimal Mode
+        Instruction::new(0xD8, "CLD", AddressingMode::Implicit, 1, 2),
+
+        // CLI - Clear Interrupt Disable
+        Instruction::new(0x58, "CLI", AddressingMode::Implicit, 1, 2),
+
+        // CLV - Clear Overflow Flag
+        Instruction::new(0xB8, "CLV", AddressingMode::Implicit, 1, 2),
+
+        // CMP - Compare
+        Instruction::new(0xC9, "CMP", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xC5, "CMP", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xD5, "CMP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0xCD, "CMP", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0xDD, "CMP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0xD9, "CMP", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0xC1, "CMP", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0xD1, "CMP", AddressingMode::IndirectY, 2, 5),
+
+        // CPX - Compare X Register
+        Instruction::new(0xE0, "CPX", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xE4, "CPX", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xEC, "CPX", AddressingMode::Absolute, 3, 4),
+
+        // CPY - Compare Y Register
+        Instruction::new(0xC0, "CPY", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xC4, "CPY", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xCC, "CPY", AddressingMode::Absolute, 3, 4),
+
+        // DEC - Decrement Memory
+        Instruction::new(0xC6, "DEC", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xD6, "DEC", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0xCE, "DEC", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0xDE, "DEC", AddressingMode::AbsoluteX, 3, 7),
+
+        // DEX - Decrement X Register
+        Instruction::new(0xCA, "DEX", AddressingMode::Implicit, 1, 2),
+
+        // DEY - Decrement Y Register
+        Instruction::new(0x88, "DEY", AddressingMode::Implicit, 1, 2),
+
+        // EOR - Exclusive OR
+        Instruction::new(0x49, "EOR", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0x45, "EOR", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x55, "EOR", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x4D, "EOR", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x5D, "EOR", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0x59, "EOR", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0x41, "EOR", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0x51, "EOR", AddressingMode::IndirectY, 2, 5),
+
+        // INC - Increment Memory
+        Instruction::new(0xE6, "INC", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xF6, "INC", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0xEE, "INC", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0xFE, "INC", AddressingMode::AbsoluteX, 3, 7),
 
         // INX - Increment X Register
-        Instruction::new(0xE8, "INX", AddressingMode::Implicit, 1),
+        Instruction::new(0xE8, "INX", AddressingMode::Implicit, 1, 2),
+
+        // INY - Increment Y Register
+        Instruction::new(0xC8, "INY", AddressingMode::Implicit, 1, 2),
+
+        // JMP - Jump
+        Instruction::new(0x4C, "JMP", AddressingMode::Absolute, 3, 3),
+        Instruction::new(0x6C, "JMP", AddressingMode::Indirect, 3, 5),
+
+        // JSR - Jump to Subroutine
+        Instruction::new(0x20, "JSR", AddressingMode::Absolute, 3, 6),
 
         // LDA - Load Accumulator
-        Instruction::new(0xA9, "LDA", AddressingMode::Immediate, 2),
+        Instruction::new(0xA9, "LDA", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xA5, "LDA", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xB5, "LDA", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0xAD, "LDA", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0xBD, "LDA", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0xB9, "LDA", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0xA1, "LDA", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0xB1, "LDA", AddressingMode::IndirectY, 2, 5),
 
         // LDX - Load X Register
-        Instruction::new(0xA2, "LDX", AddressingMode::Immediate, 2),
+        Instruction::new(0xA2, "LDX", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xA6, "LDX", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xB6, "LDX", AddressingMode::ZeroPageY, 2, 4),
+        Instruction::new(0xAE, "LDX", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0xBE, "LDX", AddressingMode::AbsoluteY, 3, 4),
 
         // LDY - Load Y Register
-        Instruction::new(0xA0, "LDY", AddressingMode::Immediate, 2),
+        Instruction::new(0xA0, "LDY", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xA4, "LDY", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xB4, "LDY", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0xAC, "LDY", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0xBC, "LDY", AddressingMode::AbsoluteX, 3, 4),
+
+        // LSR - Logical Shift Right
+        Instruction::new(0x4A, "LSR", AddressingMode::Accumulator, 1, 2),
+        Instruction::new(0x46, "LSR", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x56, "LSR", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0x4E, "LSR", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0x5E, "LSR", AddressingMode::AbsoluteX, 3, 7),
+
+        // NOP - No Operation
+        Instruction::new(0xEA, "NOP", AddressingMode::Implicit, 1, 2),
+
+        // ORA - Logical Inclusive OR
+        Instruction::new(0x09, "ORA", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0x05, "ORA", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x15, "ORA", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x0D, "ORA", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x1D, "ORA", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0x19, "ORA", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0x01, "ORA", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0x11, "ORA", AddressingMode::IndirectY, 2, 5),
+
+        // PHA - Push Accumulator
+        Instruction::new(0x48, "PHA", AddressingMode::Implicit, 1, 3),
+
+        // PHP - Push Processor Status
+        Instruction::new(0x08, "PHP", AddressingMode::Implicit, 1, 3),
+
+        // PLA - Pull Accumulator
+        Instruction::new(0x68, "PLA", AddressingMode::Implicit, 1, 4),
+
+        // PLP - Pull Processor Status
+        Instruction::new(0x28, "PLP", AddressingMode::Implicit, 1, 4),
+
+        // ROL - Rotate Left
+        Instruction::new(0x2A, "ROL", AddressingMode::Accumulator, 1, 2),
+        Instruction::new(0x26, "ROL", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x36, "ROL", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0x2E, "ROL", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0x3E, "ROL", AddressingMode::AbsoluteX, 3, 7),
+
+        // ROR - Rotate Right
+        Instruction::new(0x6A, "ROR", AddressingMode::Accumulator, 1, 2),
+        Instruction::new(0x66, "ROR", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x76, "ROR", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::new(0x6E, "ROR", AddressingMode::Absolute, 3, 6),
+        Instruction::new(0x7E, "ROR", AddressingMode::AbsoluteX, 3, 7),
+
+        // RTI - Return from Interrupt
+        Instruction::new(0x40, "RTI", AddressingMode::Implicit, 1, 6),
+
+        // RTS - Return from Subroutine
+        Instruction::new(0x60, "RTS", AddressingMode::Implicit, 1, 6),
+
+        // SBC - Subtract with Carry
+        Instruction::new(0xE9, "SBC", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0xE5, "SBC", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0xF5, "SBC", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0xED, "SBC", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0xFD, "SBC", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::new(0xF9, "SBC", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::new(0xE1, "SBC", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0xF1, "SBC", AddressingMode::IndirectY, 2, 5),
+
+        // SEC - Set Carry Flag
+        Instruction::new(0x38, "SEC", AddressingMode::Implicit, 1, 2),
+
+        // SED - Set Decimal Flag
+        Instruction::new(0xF8, "SED", AddressingMode::Implicit, 1, 2),
+
+        // SEI - Set Interrupt Disable
+        Instruction::new(0x78, "SEI", AddressingMode::Implicit, 1, 2),
 
         // TAX - Transfer Accumulator to X Register
-        Instruction::new(0xAA, "TAX", AddressingMode::Implicit, 1),
+        Instruction::new(0xAA, "TAX", AddressingMode::Implicit, 1, 2),
+
+        // TAY - Transfer Accumulator to Y Register
+        Instruction::new(0xA8, "TAY", AddressingMode::Implicit, 1, 2),
+
+        // TSX - Transfer Stack Pointer to X Register
+        Instruction::new(0xBA, "TSX", AddressingMode::Implicit, 1, 2),
+
+        // TXA - Transfer X Register to Accumulator
+        Instruction::new(0x8A, "TXA", AddressingMode::Implicit, 1, 2),
+
+        // TXS - Transfer X Register to Stack Pointer
+        Instruction::new(0x9A, "TXS", AddressingMode::Implicit, 1, 2),
+
+        // TYA - Transfer Y Register to Accumulator
+        Instruction::new(0x98, "TYA", AddressingMode::Implicit, 1, 2),
 
         // STA - Store Accumulator
-        Instruction::new(0x85, "STA", AddressingMode::ZeroPage, 2),
+        Instruction::new(0x85, "STA", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x95, "STA", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x8D, "STA", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x9D, "STA", AddressingMode::AbsoluteX, 3, 5),
+        Instruction::new(0x99, "STA", AddressingMode::AbsoluteY, 3, 5),
+        Instruction::new(0x81, "STA", AddressingMode::IndirectX, 2, 6),
+        Instruction::new(0x91, "STA", AddressingMode::IndirectY, 2, 6),
+
+        // STX - Store X Register
+        Instruction::new(0x86, "STX", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x96, "STX", AddressingMode::ZeroPageY, 2, 4),
+        Instruction::new(0x8E, "STX", AddressingMode::Absolute, 3, 4),
+
+        // STY - Store Y Register
+        Instruction::new(0x84, "STY", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x94, "STY", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x8C, "STY", AddressingMode::Absolute, 3, 4),
+
+    ];
+
+    // Reference: https://www.masswerk.at/nowgobang/2021/6502-illegal-opcodes
+    pub static ref NMOS_INSTRUCTION_SET: [Instruction; NMOS_INSTRUCTION_SET_SIZE] = [
+        // SLO - ASL then ORA
+        Instruction::undocumented(0x07, "SLO", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0x17, "SLO", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0x0F, "SLO", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0x1F, "SLO", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0x1B, "SLO", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0x03, "SLO", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0x13, "SLO", AddressingMode::IndirectY, 2, 8),
+
+        // RLA - ROL then AND
+        Instruction::undocumented(0x27, "RLA", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0x37, "RLA", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0x2F, "RLA", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0x3F, "RLA", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0x3B, "RLA", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0x23, "RLA", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0x33, "RLA", AddressingMode::IndirectY, 2, 8),
+
+        // SRE - LSR then EOR
+        Instruction::undocumented(0x47, "SRE", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0x57, "SRE", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0x4F, "SRE", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0x5F, "SRE", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0x5B, "SRE", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0x43, "SRE", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0x53, "SRE", AddressingMode::IndirectY, 2, 8),
+
+        // RRA - ROR then ADC
+        Instruction::undocumented(0x67, "RRA", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0x77, "RRA", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0x6F, "RRA", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0x7F, "RRA", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0x7B, "RRA", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0x63, "RRA", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0x73, "RRA", AddressingMode::IndirectY, 2, 8),
+
+        // DCP - DEC then CMP
+        Instruction::undocumented(0xC7, "DCP", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0xD7, "DCP", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0xCF, "DCP", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0xDF, "DCP", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0xDB, "DCP", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0xC3, "DCP", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0xD3, "DCP", AddressingMode::IndirectY, 2, 8),
+
+        // ISC - INC then SBC
+        Instruction::undocumented(0xE7, "ISC", AddressingMode::ZeroPage, 2, 5),
+        Instruction::undocumented(0xF7, "ISC", AddressingMode::ZeroPageX, 2, 6),
+        Instruction::undocumented(0xEF, "ISC", AddressingMode::Absolute, 3, 6),
+        Instruction::undocumented(0xFF, "ISC", AddressingMode::AbsoluteX, 3, 7),
+        Instruction::undocumented(0xFB, "ISC", AddressingMode::AbsoluteY, 3, 7),
+        Instruction::undocumented(0xE3, "ISC", AddressingMode::IndirectX, 2, 8),
+        Instruction::undocumented(0xF3, "ISC", AddressingMode::IndirectY, 2, 8),
+
+        // SAX - Store A AND X
+        Instruction::undocumented(0x87, "SAX", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0x97, "SAX", AddressingMode::ZeroPageY, 2, 4),
+        Instruction::undocumented(0x8F, "SAX", AddressingMode::Absolute, 3, 4),
+        Instruction::undocumented(0x83, "SAX", AddressingMode::IndirectX, 2, 6),
+
+        // LAX - LDA then TAX
+        Instruction::undocumented(0xA7, "LAX", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0xB7, "LAX", AddressingMode::ZeroPageY, 2, 4),
+        Instruction::undocumented(0xAF, "LAX", AddressingMode::Absolute, 3, 4),
+        Instruction::undocumented(0xBF, "LAX", AddressingMode::AbsoluteY, 3, 4),
+        Instruction::undocumented(0xA3, "LAX", AddressingMode::IndirectX, 2, 6),
+        Instruction::undocumented(0xB3, "LAX", AddressingMode::IndirectY, 2, 5),
+
+        // LAS - Load A, X and Stack Pointer with Memory AND Stack Pointer
+        Instruction::undocumented(0xBB, "LAS", AddressingMode::AbsoluteY, 3, 4),
+
+        // ANC - AND, copying Negative into Carry
+        Instruction::undocumented(0x0B, "ANC", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x2B, "ANC", AddressingMode::Immediate, 2, 2),
+
+        // ALR - AND then LSR
+        Instruction::undocumented(0x4B, "ALR", AddressingMode::Immediate, 2, 2),
+
+        // ARR - AND then ROR
+        Instruction::undocumented(0x6B, "ARR", AddressingMode::Immediate, 2, 2),
+
+        // SBX - Subtract from A AND X into X
+        Instruction::undocumented(0xCB, "SBX", AddressingMode::Immediate, 2, 2),
+
+        // USBC - SBC Immediate
+        Instruction::undocumented(0xEB, "USBC", AddressingMode::Immediate, 2, 2),
+
+        // ANE - A OR Magic AND X AND Immediate (unstable)
+        Instruction::undocumented(0x8B, "ANE", AddressingMode::Immediate, 2, 2),
+
+        // LXA - A OR Magic AND Immediate into A and X (unstable)
+        Instruction::undocumented(0xAB, "LXA", AddressingMode::Immediate, 2, 2),
+
+        // SHA - Store A AND X AND High Byte + 1 (unstable)
+        Instruction::undocumented(0x9F, "SHA", AddressingMode::AbsoluteY, 3, 5),
+        Instruction::undocumented(0x93, "SHA", AddressingMode::IndirectY, 2, 6),
+
+        // SHX - Store X AND High Byte + 1 (unstable)
+        Instruction::undocumented(0x9E, "SHX", AddressingMode::AbsoluteY, 3, 5),
+
+        // SHY - Store Y AND High Byte + 1 (unstable)
+        Instruction::undocumented(0x9C, "SHY", AddressingMode::AbsoluteX, 3, 5),
+
+        // TAS - Transfer A AND X to Stack Pointer, then SHA (unstable)
+        Instruction::undocumented(0x9B, "TAS", AddressingMode::AbsoluteY, 3, 5),
+
+        // NOP - No Operation, reading the operand if there is one
+        Instruction::undocumented(0x1A, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x3A, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x5A, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x7A, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0xDA, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0xFA, "NOP", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x80, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x82, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x89, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0xC2, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0xE2, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x04, "NOP", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0x44, "NOP", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0x64, "NOP", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0x14, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0x34, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0x54, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0x74, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0xD4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0xF4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0x0C, "NOP", AddressingMode::Absolute, 3, 4),
+        Instruction::undocumented(0x1C, "NOP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::undocumented(0x3C, "NOP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::undocumented(0x5C, "NOP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::undocumented(0x7C, "NOP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::undocumented(0xDC, "NOP", AddressingMode::AbsoluteX, 3, 4),
+        Instruction::undocumented(0xFC, "NOP", AddressingMode::AbsoluteX, 3, 4),
+
+        // JAM - Lock up the CPU
+        Instruction::undocumented(0x02, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x12, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x22, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x32, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x42, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x52, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x62, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x72, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0x92, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0xB2, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0xD2, "JAM", AddressingMode::Implicit, 1, 2),
+        Instruction::undocumented(0xF2, "JAM", AddressingMode::Implicit, 1, 2),
+    ];
+
+    // Reference: http://www.6502.org/tutorials/65c02opcodes.html
+    pub static ref CMOS_INSTRUCTION_SET: [Instruction; CMOS_INSTRUCTION_SET_SIZE] = [
+        // Changed from the NMOS 6502
+
+        // JMP - The indirect page wrap bug is fixed, at the cost of a cycle
+        Instruction::new(0x6C, "JMP", AddressingMode::Indirect, 3, 6),
+
+        // ASL, LSR, ROL, ROR - Absolute X only spends the fix-up cycle on a page crossing
+        Instruction::new(0x1E, "ASL", AddressingMode::AbsoluteX, 3, 6),
+        Instruction::new(0x5E, "LSR", AddressingMode::AbsoluteX, 3, 6),
+        Instruction::new(0x3E, "ROL", AddressingMode::AbsoluteX, 3, 6),
+        Instruction::new(0x7E, "ROR", AddressingMode::AbsoluteX, 3, 6),
+
+        // Added by the 65C02
+
+        // ADC, AND, CMP, EOR, LDA, ORA, SBC, STA - Zero Page Indirect
+        Instruction::new(0x72, "ADC", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0x32, "AND", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0xD2, "CMP", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0x52, "EOR", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0xB2, "LDA", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0x12, "ORA", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0xF2, "SBC", AddressingMode::ZeroPageIndirect, 2, 5),
+        Instruction::new(0x92, "STA", AddressingMode::ZeroPageIndirect, 2, 5),
+
+        // BIT - Bit Test. The immediate form only affects Zero.
+        Instruction::new(0x89, "BIT", AddressingMode::Immediate, 2, 2),
+        Instruction::new(0x34, "BIT", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x3C, "BIT", AddressingMode::AbsoluteX, 3, 4),
+
+        // BRA - Branch Always
+        Instruction::new(0x80, "BRA", AddressingMode::Relative, 2, 2),
+
+        // JMP - Absolute Indexed Indirect
+        Instruction::new(0x7C, "JMP", AddressingMode::AbsoluteIndexedIndirect, 3, 6),
+
+        // DEC, INC - Accumulator
+        Instruction::new(0x3A, "DEC", AddressingMode::Accumulator, 1, 2),
+        Instruction::new(0x1A, "INC", AddressingMode::Accumulator, 1, 2),
+
+        // PHX - Push X Register
+        Instruction::new(0xDA, "PHX", AddressingMode::Implicit, 1, 3),
+
+        // PHY - Push Y Register
+        Instruction::new(0x5A, "PHY", AddressingMode::Implicit, 1, 3),
+
+        // PLX - Pull X Register
+        Instruction::new(0xFA, "PLX", AddressingMode::Implicit, 1, 4),
+
+        // PLY - Pull Y Register
+        Instruction::new(0x7A, "PLY", AddressingMode::Implicit, 1, 4),
+
+        // STZ - Store Zero
+        Instruction::new(0x64, "STZ", AddressingMode::ZeroPage, 2, 3),
+        Instruction::new(0x74, "STZ", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::new(0x9C, "STZ", AddressingMode::Absolute, 3, 4),
+        Instruction::new(0x9E, "STZ", AddressingMode::AbsoluteX, 3, 5),
+
+        // TRB - Test and Reset Bits
+        Instruction::new(0x14, "TRB", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x1C, "TRB", AddressingMode::Absolute, 3, 6),
+
+        // TSB - Test and Set Bits
+        Instruction::new(0x04, "TSB", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x0C, "TSB", AddressingMode::Absolute, 3, 6),
+
+        // NOP - Every other opcode is a NOP, some of which read an operand
+        Instruction::undocumented(0x02, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x03, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x07, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x0B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x0F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x13, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x17, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x1B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x1F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x22, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x23, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x27, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x2B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x2F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x33, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x37, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x3B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x3F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x42, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x43, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x44, "NOP", AddressingMode::ZeroPage, 2, 3),
+        Instruction::undocumented(0x47, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x4B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x4F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x53, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x54, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0x57, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x5B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x5C, "NOP", AddressingMode::Absolute, 3, 8),
+        Instruction::undocumented(0x5F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x62, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x63, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x67, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x6B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x6F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x73, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x77, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x7B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x7F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x82, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0x83, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x87, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x8B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x8F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x93, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x97, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x9B, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0x9F, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xA3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xA7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xAB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xAF, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xB3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xB7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xBB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xBF, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xC2, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0xC3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xC7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xCB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xCF, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xD3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xD4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0xD7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xDB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xDC, "NOP", AddressingMode::Absolute, 3, 4),
+        Instruction::undocumented(0xDF, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xE2, "NOP", AddressingMode::Immediate, 2, 2),
+        Instruction::undocumented(0xE3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xE7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xEB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xEF, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xF3, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xF4, "NOP", AddressingMode::ZeroPageX, 2, 4),
+        Instruction::undocumented(0xF7, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xFB, "NOP", AddressingMode::Implicit, 1, 1),
+        Instruction::undocumented(0xFC, "NOP", AddressingMode::Absolute, 3, 4),
+        Instruction::undocumented(0xFF, "NOP", AddressingMode::Implicit, 1, 1),
+    ];
+
+    pub static ref WDC_INSTRUCTION_SET: [Instruction; WDC_INSTRUCTION_SET_SIZE] = [
+        // BBR0-BBR7 - Branch on Bit Reset
+        Instruction::new(0x0F, "BBR0", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x1F, "BBR1", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x2F, "BBR2", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x3F, "BBR3", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x4F, "BBR4", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x5F, "BBR5", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x6F, "BBR6", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x7F, "BBR7", AddressingMode::ZeroPageRelative, 3, 5),
+
+        // BBS0-BBS7 - Branch on Bit Set
+        Instruction::new(0x8F, "BBS0", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0x9F, "BBS1", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xAF, "BBS2", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xBF, "BBS3", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xCF, "BBS4", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xDF, "BBS5", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xEF, "BBS6", AddressingMode::ZeroPageRelative, 3, 5),
+        Instruction::new(0xFF, "BBS7", AddressingMode::ZeroPageRelative, 3, 5),
+
+        // RMB0-RMB7 - Reset Memory Bit
+        Instruction::new(0x07, "RMB0", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x17, "RMB1", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x27, "RMB2", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x37, "RMB3", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x47, "RMB4", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x57, "RMB5", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x67, "RMB6", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x77, "RMB7", AddressingMode::ZeroPage, 2, 5),
+
+        // SMB0-SMB7 - Set Memory Bit
+        Instruction::new(0x87, "SMB0", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0x97, "SMB1", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xA7, "SMB2", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xB7, "SMB3", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xC7, "SMB4", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xD7, "SMB5", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xE7, "SMB6", AddressingMode::ZeroPage, 2, 5),
+        Instruction::new(0xF7, "SMB7", AddressingMode::ZeroPage, 2, 5),
+
+        // STP - Stop the Clock
+        Instruction::new(0xDB, "STP", AddressingMode::Implicit, 1, 3),
+
+        // WAI - Wait for Interrupt
+        Instruction::new(0xCB, "WAI", AddressingMode::Implicit, 1, 3),
     ];
 
-    pub static ref INSTRUCTION_MAP: HashMap<u8, &'static Instruction> = {
-        let mut map = HashMap::new();
+    // Each variant layers its instructions over the documented set
+    pub static ref NMOS_INSTRUCTION_MAP: HashMap<u8, &'static Instruction> =
+        build_map(&[&INSTRUCTION_SET[..], &NMOS_INSTRUCTION_SET[..]]);
+
+    pub static ref CMOS_INSTRUCTION_MAP: HashMap<u8, &'static Instruction> =
+        build_map(&[&INSTRUCTION_SET[..], &CMOS_INSTRUCTION_SET[..]]);
+
+    pub static ref WDC_INSTRUCTION_MAP: HashMap<u8, &'static Instruction> = build_map(&[
+        &INSTRUCTION_SET[..],
+        &CMOS_INSTRUCTION_SET[..],
+        &WDC_INSTRUCTION_SET[..],
+    ]);
+}
+
+// Later sets replace entries of earlier ones with the same opcode
+fn build_map(sets: &[&'static [Instruction]]) -> HashMap<u8, &'static Instruction> {
+    let mut map = HashMap::new();
 
-        let mut i = 0;
-        while i < INSTRUCTION_SET_SIZE {
-            let instruction = &INSTRUCTION_SET[i];
+    for set in sets {
+        for instruction in set.iter() {
             map.insert(instruction.opcode, instruction);
-            i += 1;
         }
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lengths_match_addressing_modes() {
+        let maps = [
+            &*NMOS_INSTRUCTION_MAP,
+            &*CMOS_INSTRUCTION_MAP,
+            &*WDC_INSTRUCTION_MAP,
+        ];
+        for map in maps {
+            for instruction in map.values() {
+                let length = 1 + instruction.mode.operand_length();
+                assert_eq!(instruction.length, length, "{:#04X}", instruction.opcode);
+            }
+        }
+    }
 
-        map
-    };
+    #[test]
+    fn test_cmos_decodes_every_opcode() {
+        assert_eq!(CMOS_INSTRUCTION_MAP.len(), 256);
+        assert_eq!(WDC_INSTRUCTION_MAP.len(), 256);
+    }
 }
@@ -1,20 +1,45 @@
 pub mod addressing_mode;
 use addressing_mode::AddressingMode;
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct Instruction {
     pub opcode: u8,
     pub mnemonic: &'static str,
     pub mode: AddressingMode,
     pub length: u8, /* in bytes */
+    pub cycles: u8, /* base count, before page crossing and branch penalties */
+    pub documented: bool,
 }
 
 impl Instruction {
-    pub const fn new(opcode: u8, mnemonic: &'static str, mode: AddressingMode, length: u8) -> Self {
+    pub const fn new(
+        opcode: u8,
+        mnemonic: &'static str,
+        mode: AddressingMode,
+        length: u8,
+        cycles: u8,
+    ) -> Self {
         Instruction {
             opcode,
             mnemonic,
             mode,
             length,
+            cycles,
+            documented: true,
+        }
+    }
+
+    // Opcodes the NMOS 6502 decodes without them being part of the official set
+    pub const fn undocumented(
+        opcode: u8,
+        mnemonic: &'static str,
+        mode: AddressingMode,
+        length: u8,
+        cycles: u8,
+    ) -> Self {
+        Instruction {
+            documented: false,
+            ..Instruction::new(opcode, mnemonic, mode, length, cycles)
         }
     }
 }
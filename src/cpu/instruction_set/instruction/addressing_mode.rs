@@ -1,6 +1,8 @@
 // Reference: https://www.nesdev.org/obelisk-6502-guide/addressing.html#REL
 
-#[derive(Debug)]
+use crate::util;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AddressingMode {
     Implicit,
     Accumulator,
@@ -12,6 +14,114 @@ pub enum AddressingMode {
     Absolute,
     AbsoluteX,
     AbsoluteY,
-    IndirectX, /* Indexed Indirect */
-    IndirectY, /* Indirect Indexed */
+    Indirect,
+    IndirectX,               /* Indexed Indirect */
+    IndirectY,               /* Indirect Indexed */
+    ZeroPageIndirect,        /* 65C02 */
+    AbsoluteIndexedIndirect, /* 65C02 JMP (abs,X) */
+    ZeroPageRelative,        /* Rockwell bit branches: a zero page address, then an offset */
+}
+
+impl AddressingMode {
+    // Number of operand bytes following the opcode
+    pub fn operand_length(self) -> u8 {
+        match self {
+            AddressingMode::Implicit | AddressingMode::Accumulator => 0,
+
+            AddressingMode::Immediate
+            | AddressingMode::ZeroPage
+            | AddressingMode::ZeroPageX
+            | AddressingMode::ZeroPageY
+            | AddressingMode::Relative
+            | AddressingMode::IndirectX
+            | AddressingMode::IndirectY
+            | AddressingMode::ZeroPageIndirect => 1,
+
+            AddressingMode::Absolute
+            | AddressingMode::AbsoluteX
+            | AddressingMode::AbsoluteY
+            | AddressingMode::Indirect
+            | AddressingMode::AbsoluteIndexedIndirect
+            | AddressingMode::ZeroPageRelative => 2,
+        }
+    }
+
+    // Renders the operand in the usual assembler syntax, e.g. `($20),Y`. `operand` holds
+    // the bytes after the opcode and must be `operand_length` long. Branch targets are
+    // resolved against `address`, the address of the opcode.
+    pub fn format_operand(self, operand: &[u8], address: u16) -> String {
+        let byte = || operand[0];
+        let word = || u16::from_le_bytes([operand[0], operand[1]]);
+
+        match self {
+            AddressingMode::Implicit => String::new(),
+            AddressingMode::Accumulator => String::from("A"),
+            AddressingMode::Immediate => format!("#${:02X}", byte()),
+            AddressingMode::ZeroPage => format!("${:02X}", byte()),
+            AddressingMode::ZeroPageX => format!("${:02X},X", byte()),
+            AddressingMode::ZeroPageY => format!("${:02X},Y", byte()),
+            AddressingMode::Relative => {
+                let target = util::get_address_from_offset(address.wrapping_add(2), byte());
+                format!("${:04X}", target)
+            }
+            AddressingMode::Absolute => format!("${:04X}", word()),
+            AddressingMode::AbsoluteX => format!("${:04X},X", word()),
+            AddressingMode::AbsoluteY => format!("${:04X},Y", word()),
+            AddressingMode::Indirect => format!("(${:04X})", word()),
+            AddressingMode::IndirectX => format!("(${:02X},X)", byte()),
+            AddressingMode::IndirectY => format!("(${:02X}),Y", byte()),
+            AddressingMode::ZeroPageIndirect => format!("(${:02X})", byte()),
+            AddressingMode::AbsoluteIndexedIndirect => format!("(${:04X},X)", word()),
+            AddressingMode::ZeroPageRelative => {
+                let target = util::get_address_from_offset(address.wrapping_add(3), operand[1]);
+                format!("${:02X},${:04X}", operand[0], target)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_operand() {
+        let cases = [
+            (AddressingMode::Implicit, vec![], ""),
+            (AddressingMode::Accumulator, vec![], "A"),
+            (AddressingMode::Immediate, vec![0x05], "#$05"),
+            (AddressingMode::ZeroPageY, vec![0x10], "$10,Y"),
+            (AddressingMode::AbsoluteX, vec![0x20, 0x43], "$4320,X"),
+            (AddressingMode::Indirect, vec![0xff, 0x30], "($30FF)"),
+            (AddressingMode::IndirectX, vec![0x20], "($20,X)"),
+            (AddressingMode::IndirectY, vec![0x20], "($20),Y"),
+            (AddressingMode::ZeroPageIndirect, vec![0x20], "($20)"),
+            (
+                AddressingMode::AbsoluteIndexedIndirect,
+                vec![0x00, 0x90],
+                "($9000,X)",
+            ),
+        ];
+
+        for (mode, operand, expected) in cases {
+            assert_eq!(operand.len(), mode.operand_length() as usize);
+            assert_eq!(mode.format_operand(&operand, 0x8000), expected);
+        }
+    }
+
+    #[test]
+    fn test_format_branch_targets() {
+        assert_eq!(
+            AddressingMode::Relative.format_operand(&[0x02], 0x8004),
+            "$8008"
+        );
+        assert_eq!(
+            AddressingMode::Relative.format_operand(&[0xfc], 0x8004),
+            "$8002"
+        );
+        assert_eq!(
+            AddressingMode::ZeroPageRelative.format_operand(&[0x10, 0xfd], 0x8000),
+            "$10,$8000"
+        );
+    }
 }
@@ -0,0 +1,1772 @@
+// Cycle-accurate execution core. Each call to `CPU::tick` performs exactly one bus
+// access, following the cycle-by-cycle behaviour documented in "64doc" by John West and
+// Marko Mäkelä, including the dummy reads and the double write of read-modify-write
+// instructions. The addressing is done here; the instruction itself then runs through
+// the same `execute` as the fast core, with its operand taken from the latch.
+// Reference: http://www.6502.org/tutorials/interrupts.html (interrupt sequence)
+//
+// The 65C02 variants replace the dummy write of read-modify-write instructions with a
+// second read, but otherwise reuse the NMOS dummy access addresses.
+
+use super::bus::Bus;
+use super::error::CpuError;
+use super::instruction_set::instruction::addressing_mode::AddressingMode;
+use super::status_flag::StatusFlag;
+use super::{CPU, IRQ_BRK_VECTOR, NMI_VECTOR, STACK};
+use crate::util;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusAccess {
+    Read { address: u16, data: u8 },
+    Write { address: u16, data: u8 },
+}
+
+// How an instruction uses its effective address
+#[derive(PartialEq)]
+enum Access {
+    Read,
+    Write,
+    ReadModifyWrite,
+}
+
+fn access_of(mnemonic: &str) -> Access {
+    match mnemonic {
+        "STA" | "STX" | "STY" | "STZ" | "SAX" | "SHA" | "SHX" | "SHY" | "TAS" => Access::Write,
+        "ASL" | "LSR" | "ROL" | "ROR" | "INC" | "DEC" | "TRB" | "TSB" | "SLO" | "RLA" | "SRE"
+        | "RRA" | "DCP" | "ISC" => Access::ReadModifyWrite,
+        _ if mnemonic.starts_with("RMB") || mnemonic.starts_with("SMB") => Access::ReadModifyWrite,
+        _ => Access::Read,
+    }
+}
+
+// Progress through the current instruction. Cycle 0 is the opcode fetch.
+#[derive(Default)]
+pub struct MicroState {
+    cycle: u8,
+    opcode: u8,
+    opcode_address: u16,
+    // Vector of a hardware interrupt being serviced in place of an instruction
+    interrupt: Option<u16>,
+    base: u16,
+    address: u16,
+    pointer: u8,
+    data: u8,
+    // The 65C02 spends one more cycle on decimal ADC and SBC
+    decimal_cycle: bool,
+}
+
+impl<B: Bus> CPU<B> {
+    // Runs one clock cycle
+    pub fn tick(&mut self) -> Result<BusAccess, CpuError> {
+        if self.jammed {
+            return Err(CpuError::Jammed {
+                pc: self.program_counter,
+            });
+        }
+        if self.stopped {
+            return Err(CpuError::Stopped {
+                pc: self.program_counter,
+            });
+        }
+
+        self.cycles += 1;
+
+        if self.waiting {
+            if self.wakes_up() {
+                self.waiting = false;
+            } else {
+                return Ok(self.read_cycle(self.program_counter));
+            }
+        }
+
+        let cycle = self.micro.cycle;
+        self.micro.cycle += 1;
+
+        let access = if cycle == 0 {
+            self.tick_fetch()?
+        } else {
+            self.tick_instruction(cycle)
+        };
+
+        self.check_bus_fault(self.micro.opcode_address)?;
+
+        // Like `step`, report JAM and STP on the cycle that executes them
+        if self.jammed {
+            return Err(CpuError::Jammed {
+                pc: self.micro.opcode_address,
+            });
+        }
+        if self.stopped {
+            return Err(CpuError::Stopped {
+                pc: self.micro.opcode_address,
+            });
+        }
+        Ok(access)
+    }
+
+    // True when the next tick fetches a new opcode
+    pub fn at_instruction_boundary(&self) -> bool {
+        self.micro.cycle == 0
+    }
+
+    // The instruction-level core can only start where `tick` has finished an
+    // instruction. It cannot pick up the state of one in flight.
+    pub(super) fn check_instruction_boundary(&self) -> Result<(), CpuError> {
+        if self.at_instruction_boundary() {
+            Ok(())
+        } else {
+            Err(CpuError::InstructionInProgress {
+                pc: self.micro.opcode_address,
+            })
+        }
+    }
+
+    fn tick_instruction(&mut self, cycle: u8) -> BusAccess {
+        if let Some(vector) = self.micro.interrupt {
+            return self.tick_interrupt(cycle, vector, false);
+        }
+
+        if self.micro.decimal_cycle {
+            self.micro.decimal_cycle = false;
+            self.finish();
+            return self.read_cycle(self.program_counter);
+        }
+
+        let instruction = self.variant.instruction_map()[&self.micro.opcode];
+
+        match (instruction.mnemonic, &instruction.mode) {
+            ("BRK", _) => self.tick_interrupt(cycle, IRQ_BRK_VECTOR, true),
+
+            ("JSR", _) => self.tick_jsr(cycle),
+
+            ("RTS", _) => self.tick_rts(cycle),
+
+            ("RTI", _) => self.tick_rti(cycle),
+
+            ("PHA" | "PHP" | "PHX" | "PHY", _) => self.tick_push(cycle),
+
+            ("PLA" | "PLP" | "PLX" | "PLY", _) => self.tick_pull(cycle),
+
+            ("JMP", mode) => self.tick_jmp(cycle, mode),
+
+            ("WAI" | "STP", mode) => self.tick_wait_or_stop(cycle, mode),
+
+            ("NOP", _) if self.variant.is_cmos() && self.micro.opcode == 0x5C => {
+                self.tick_long_nop(cycle)
+            }
+
+            (_, AddressingMode::Relative) => self.tick_branch(cycle),
+
+            (_, AddressingMode::ZeroPageRelative) => self.tick_bit_branch(cycle),
+
+            (_, mode) => {
+                let access = access_of(instruction.mnemonic);
+                self.tick_addressing(cycle, mode, access)
+            }
+        }
+    }
+
+    fn read_cycle(&mut self, address: u16) -> BusAccess {
+        let data = self.mem_read(address);
+        self.micro.data = data;
+        BusAccess::Read { address, data }
+    }
+
+    fn write_cycle(&mut self, address: u16, data: u8) -> BusAccess {
+        self.mem_write(address, data);
+        BusAccess::Write { address, data }
+    }
+
+    fn read_program_counter(&mut self) -> BusAccess {
+        let access = self.read_cycle(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        access
+    }
+
+    fn finish(&mut self) {
+        self.micro.cycle = 0;
+        self.micro.interrupt = None;
+    }
+
+    // Ends an instruction that read its operand, unless it still owes a decimal cycle
+    fn finish_read(&mut self) {
+        let mnemonic = self.variant.instruction_map()[&self.micro.opcode].mnemonic;
+        if self.decimal_penalty(mnemonic) {
+            self.micro.decimal_cycle = true;
+        } else {
+            self.finish();
+        }
+    }
+
+    // Runs the instruction against an operand that has already been fetched. Returns
+    // the address and value to write back; the unstable stores may change the address.
+    fn execute_latched(&mut self, mode: &AddressingMode, address: u16, value: u8) -> (u16, u8) {
+        self.operand_latch = Some((address, value));
+        self.execute(self.micro.opcode, mode);
+        self.operand_latch.take().unwrap()
+    }
+
+    fn tick_fetch(&mut self) -> Result<BusAccess, CpuError> {
+        self.page_crossed = false;
+        self.micro.opcode_address = self.program_counter;
+
+        // A pending interrupt replaces the opcode fetch; the program counter is not
+        // advanced so the interrupted instruction runs after RTI
+        if self.interrupt_pending() {
+            let vector = if self.nmi_pending {
+                self.nmi_pending = false;
+                NMI_VECTOR
+            } else {
+                IRQ_BRK_VECTOR
+            };
+            self.micro.interrupt = Some(vector);
+            return Ok(self.read_cycle(self.program_counter));
+        }
+
+        let access = self.read_program_counter();
+        match self.decode(self.micro.data, self.micro.opcode_address) {
+            Ok(instruction) => {
+                self.micro.opcode = instruction.opcode;
+                // The 65C02's one-cycle NOPs are over once their opcode is fetched
+                if instruction.cycles == 1 {
+                    self.finish();
+                }
+                Ok(access)
+            }
+            Err(error) => {
+                self.finish();
+                Err(error)
+            }
+        }
+    }
+
+    fn tick_interrupt(&mut self, cycle: u8, vector: u16, break_flag: bool) -> BusAccess {
+        match cycle {
+            // BRK skips its padding byte, a hardware interrupt re-reads the opcode
+            1 if break_flag => self.read_program_counter(),
+            1 => self.read_cycle(self.program_counter),
+            2 => self.push_cycle((self.program_counter >> 8) as u8),
+            3 => self.push_cycle(self.program_counter as u8),
+            4 => {
+                let status = self.status_for_push(break_flag);
+                self.mask_interrupts();
+                self.push_cycle(status)
+            }
+            5 => {
+                let access = self.read_cycle(vector);
+                self.micro.address = self.micro.data as u16;
+                access
+            }
+            _ => {
+                let access = self.read_cycle(vector.wrapping_add(1));
+                self.program_counter = (self.micro.data as u16) << 8 | self.micro.address;
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn push_cycle(&mut self, data: u8) -> BusAccess {
+        let access = self.write_cycle(STACK + self.stack_pointer as u16, data);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+        access
+    }
+
+    fn pull_cycle(&mut self) -> BusAccess {
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        self.read_cycle(STACK + self.stack_pointer as u16)
+    }
+
+    fn tick_jsr(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => {
+                let access = self.read_program_counter();
+                self.micro.address = self.micro.data as u16;
+                access
+            }
+            2 => self.read_cycle(STACK + self.stack_pointer as u16),
+            3 => self.push_cycle((self.program_counter >> 8) as u8),
+            4 => self.push_cycle(self.program_counter as u8),
+            _ => {
+                let access = self.read_cycle(self.program_counter);
+                self.program_counter = (self.micro.data as u16) << 8 | self.micro.address;
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn tick_rts(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => self.read_cycle(self.program_counter),
+            2 => self.read_cycle(STACK + self.stack_pointer as u16),
+            3 => {
+                let access = self.pull_cycle();
+                self.micro.address = self.micro.data as u16;
+                access
+            }
+            4 => {
+                let access = self.pull_cycle();
+                self.program_counter = (self.micro.data as u16) << 8 | self.micro.address;
+                access
+            }
+            _ => {
+                let access = self.read_program_counter();
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn tick_rti(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => self.read_cycle(self.program_counter),
+            2 => self.read_cycle(STACK + self.stack_pointer as u16),
+            3 => {
+                let access = self.pull_cycle();
+                self.restore_status(self.micro.data);
+                access
+            }
+            4 => {
+                let access = self.pull_cycle();
+                self.micro.address = self.micro.data as u16;
+                access
+            }
+            _ => {
+                let access = self.pull_cycle();
+                self.program_counter = (self.micro.data as u16) << 8 | self.micro.address;
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn tick_push(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => self.read_cycle(self.program_counter),
+            _ => {
+                let data = match self.micro.opcode {
+                    0x48 => self.register_a,
+                    0xDA => self.register_x,
+                    0x5A => self.register_y,
+                    _ => self.status_for_push(true),
+                };
+                let access = self.push_cycle(data);
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn tick_pull(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => self.read_cycle(self.program_counter),
+            2 => self.read_cycle(STACK + self.stack_pointer as u16),
+            _ => {
+                let access = self.pull_cycle();
+                let data = self.micro.data;
+                match self.micro.opcode {
+                    0x68 => self.register_a = data,
+                    0xFA => self.register_x = data,
+                    0x7A => self.register_y = data,
+                    _ => self.restore_status(data),
+                }
+                if self.micro.opcode != 0x28 {
+                    self.update_zero_and_negative_flags(data);
+                }
+                self.finish();
+                access
+            }
+        }
+    }
+
+    fn tick_jmp(&mut self, cycle: u8, mode: &AddressingMode) -> BusAccess {
+        match (cycle, mode) {
+            (1, _) => {
+                let access = self.read_program_counter();
+                self.micro.base = self.micro.data as u16;
+                access
+            }
+            (2, AddressingMode::Absolute) => {
+                let access = self.read_cycle(self.program_counter);
+                self.program_counter = (self.micro.data as u16) << 8 | self.micro.base;
+                self.finish();
+                access
+            }
+            (2, _) => {
+                let access = self.read_program_counter();
+                self.micro.base |= (self.micro.data as u16) << 8;
+                if let AddressingMode::AbsoluteIndexedIndirect = mode {
+                    self.micro.base = self.micro.base.wrapping_add(self.register_x as u16);
+                }
+                access
+            }
+            // The 65C02 spends a cycle fixing the page wrap bug, or adding X, before
+            // reading the vector
+            (3, _) if self.variant.is_cmos() => {
+                self.read_cycle(self.program_counter.wrapping_sub(1))
+            }
+            _ => self.tick_jmp_vector(cycle - self.variant.is_cmos() as u8),
+        }
+    }
+
+    fn tick_jmp_vector(&mut self, cycle: u8) -> BusAccess {
+        let base = self.micro.base;
+        if cycle == 3 {
+            let access = self.read_cycle(base);
+            self.micro.address = self.micro.data as u16;
+            return access;
+        }
+
+        // NMOS bug: the high byte comes from the same page as the low byte
+        let high = if self.variant.is_cmos() {
+            base.wrapping_add(1)
+        } else {
+            (base & 0xFF00) | (base as u8).wrapping_add(1) as u16
+        };
+        let access = self.read_cycle(high);
+        self.program_counter = (self.micro.data as u16) << 8 | self.micro.address;
+        self.finish();
+        access
+    }
+
+    fn tick_wait_or_stop(&mut self, cycle: u8, mode: &AddressingMode) -> BusAccess {
+        let access = self.read_cycle(self.program_counter);
+        if cycle == 2 {
+            self.execute(self.micro.opcode, mode);
+            self.finish();
+        }
+        access
+    }
+
+    // The 65C02's $5C takes eight cycles. After its two operand bytes, the remaining
+    // reads are not well documented; they go to $FFxx with the low operand byte here,
+    // which keeps them clear of I/O.
+    fn tick_long_nop(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => {
+                let access = self.read_program_counter();
+                self.micro.address = 0xFF00 | self.micro.data as u16;
+                access
+            }
+            2 => self.read_program_counter(),
+            3..=6 => self.read_cycle(self.micro.address),
+            _ => {
+                let access = self.read_cycle(self.micro.address);
+                self.finish();
+                access
+            }
+        }
+    }
+
+    // BBR and BBS read their zero page operand, then continue like a branch whose offset
+    // is fetched on cycle 4
+    fn tick_bit_branch(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => {
+                let access = self.read_program_counter();
+                self.micro.address = self.micro.data as u16;
+                access
+            }
+            2 => {
+                let access = self.read_cycle(self.micro.address);
+                self.micro.pointer = self.micro.data;
+                access
+            }
+            3 => self.read_cycle(self.micro.address),
+            _ => self.tick_branch(cycle - 3),
+        }
+    }
+
+    fn branch_condition(&mut self) -> bool {
+        let opcode = self.micro.opcode;
+        if opcode & 0x0F == 0x0F {
+            // BBR, BBS: the tested value was stashed in the pointer
+            let bit = (opcode >> 4) & 0b0111;
+            return util::get_bit_at(self.micro.pointer, bit) == (opcode & 0x80 != 0);
+        }
+
+        match opcode {
+            0x80 => true,
+            0x10 => !self.is_flag_set(StatusFlag::Negative),
+            0x30 => self.is_flag_set(StatusFlag::Negative),
+            0x50 => !self.is_flag_set(StatusFlag::Overflow),
+            0x70 => self.is_flag_set(StatusFlag::Overflow),
+            0x90 => !self.is_flag_set(StatusFlag::Carry),
+            0xB0 => self.is_flag_set(StatusFlag::Carry),
+            0xD0 => !self.is_flag_set(StatusFlag::Zero),
+            _ => self.is_flag_set(StatusFlag::Zero),
+        }
+    }
+
+    fn tick_branch(&mut self, cycle: u8) -> BusAccess {
+        match cycle {
+            1 => {
+                let access = self.read_program_counter();
+                if !self.branch_condition() {
+                    self.finish();
+                }
+                access
+            }
+            2 => {
+                let offset = self.micro.data;
+                let access = self.read_cycle(self.program_counter);
+                let target = util::get_address_from_offset(self.program_counter, offset);
+                if util::is_page_crossed(self.program_counter, target) {
+                    // The low byte is updated first; the high byte is fixed next cycle
+                    self.micro.address = target;
+                    self.program_counter = (self.program_counter & 0xFF00) | (target & 0x00FF);
+                } else {
+                    self.program_counter = target;
+                    self.finish();
+                }
+                access
+            }
+            _ => {
+                let access = self.read_cycle(self.program_counter);
+                self.program_counter = self.micro.address;
+                self.finish();
+                access
+            }
+        }
+    }
+
+    // Address calculation for everything else. Once the effective address is known,
+    // the remaining cycles are handed to `tick_operand`.
+    fn tick_addressing(&mut self, cycle: u8, mode: &AddressingMode, access: Access) -> BusAccess {
+        match (mode, cycle) {
+            (AddressingMode::Implicit | AddressingMode::Accumulator, _) => {
+                let bus_access = self.read_cycle(self.program_counter);
+                self.execute(self.micro.opcode, mode);
+                self.finish();
+                bus_access
+            }
+
+            (AddressingMode::Immediate, _) => {
+                let address = self.program_counter;
+                let bus_access = self.read_program_counter();
+                self.execute_latched(mode, address, self.micro.data);
+                self.finish_read();
+                bus_access
+            }
+
+            (AddressingMode::ZeroPage, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.address = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::ZeroPage, _) => self.tick_operand(cycle - 2, mode, access),
+
+            (AddressingMode::ZeroPageX | AddressingMode::ZeroPageY, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.base = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::ZeroPageX | AddressingMode::ZeroPageY, 2) => {
+                let index = if let AddressingMode::ZeroPageX = mode {
+                    self.register_x
+                } else {
+                    self.register_y
+                };
+                let bus_access = self.read_cycle(self.micro.base);
+                self.micro.address = (self.micro.base as u8).wrapping_add(index) as u16;
+                bus_access
+            }
+            (AddressingMode::ZeroPageX | AddressingMode::ZeroPageY, _) => {
+                self.tick_operand(cycle - 3, mode, access)
+            }
+
+            (AddressingMode::Absolute, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.address = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::Absolute, 2) => {
+                let bus_access = self.read_program_counter();
+                self.micro.address |= (self.micro.data as u16) << 8;
+                bus_access
+            }
+            (AddressingMode::Absolute, _) => self.tick_operand(cycle - 3, mode, access),
+
+            (AddressingMode::AbsoluteX | AddressingMode::AbsoluteY, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.base = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::AbsoluteX | AddressingMode::AbsoluteY, 2) => {
+                let index = if let AddressingMode::AbsoluteX = mode {
+                    self.register_x
+                } else {
+                    self.register_y
+                };
+                let bus_access = self.read_program_counter();
+                self.micro.base |= (self.micro.data as u16) << 8;
+                self.micro.address = self.micro.base.wrapping_add(index as u16);
+                bus_access
+            }
+            (AddressingMode::AbsoluteX | AddressingMode::AbsoluteY, 3) => {
+                self.tick_unfixed_read(mode, access)
+            }
+            (AddressingMode::AbsoluteX | AddressingMode::AbsoluteY, _) => {
+                self.tick_operand(cycle - 4, mode, access)
+            }
+
+            (AddressingMode::IndirectX, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.pointer = self.micro.data;
+                bus_access
+            }
+            (AddressingMode::IndirectX, 2) => {
+                let bus_access = self.read_cycle(self.micro.pointer as u16);
+                self.micro.pointer = self.micro.pointer.wrapping_add(self.register_x);
+                bus_access
+            }
+            (AddressingMode::IndirectX, 3) => {
+                let bus_access = self.read_cycle(self.micro.pointer as u16);
+                self.micro.address = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::IndirectX, 4) => {
+                let bus_access = self.read_cycle(self.micro.pointer.wrapping_add(1) as u16);
+                self.micro.address |= (self.micro.data as u16) << 8;
+                bus_access
+            }
+            (AddressingMode::IndirectX, _) => self.tick_operand(cycle - 5, mode, access),
+
+            (AddressingMode::IndirectY, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.pointer = self.micro.data;
+                bus_access
+            }
+            (AddressingMode::IndirectY, 2) => {
+                let bus_access = self.read_cycle(self.micro.pointer as u16);
+                self.micro.base = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::IndirectY, 3) => {
+                let bus_access = self.read_cycle(self.micro.pointer.wrapping_add(1) as u16);
+                self.micro.base |= (self.micro.data as u16) << 8;
+                self.micro.address = self.micro.base.wrapping_add(self.register_y as u16);
+                bus_access
+            }
+            (AddressingMode::IndirectY, 4) => self.tick_unfixed_read(mode, access),
+            (AddressingMode::IndirectY, _) => self.tick_operand(cycle - 5, mode, access),
+
+            (AddressingMode::ZeroPageIndirect, 1) => {
+                let bus_access = self.read_program_counter();
+                self.micro.pointer = self.micro.data;
+                bus_access
+            }
+            (AddressingMode::ZeroPageIndirect, 2) => {
+                let bus_access = self.read_cycle(self.micro.pointer as u16);
+                self.micro.address = self.micro.data as u16;
+                bus_access
+            }
+            (AddressingMode::ZeroPageIndirect, 3) => {
+                let bus_access = self.read_cycle(self.micro.pointer.wrapping_add(1) as u16);
+                self.micro.address |= (self.micro.data as u16) << 8;
+                bus_access
+            }
+            (AddressingMode::ZeroPageIndirect, _) => self.tick_operand(cycle - 4, mode, access),
+
+            (
+                AddressingMode::Relative
+                | AddressingMode::Indirect
+                | AddressingMode::AbsoluteIndexedIndirect
+                | AddressingMode::ZeroPageRelative,
+                _,
+            ) => {
+                unreachable!("mode {:?} is handled by its instruction", mode)
+            }
+        }
+    }
+
+    // Indexed modes first read from the address before the carry into the high byte is
+    // applied. For reads that do not cross a page this is the real operand.
+    fn tick_unfixed_read(&mut self, mode: &AddressingMode, access: Access) -> BusAccess {
+        let base = self.micro.base;
+        let address = self.micro.address;
+        let unfixed = (base & 0xFF00) | (address & 0x00FF);
+
+        let bus_access = self.read_cycle(unfixed);
+        if util::is_page_crossed(base, address) {
+            return bus_access;
+        }
+        match access {
+            Access::Read => {
+                self.execute_latched(mode, address, self.micro.data);
+                self.finish_read();
+            }
+            // The 65C02 shifts skip the fix-up cycle too, so this read is the operand
+            Access::ReadModifyWrite if self.is_cmos_shift() => self.micro.cycle += 1,
+            _ => {}
+        }
+        bus_access
+    }
+
+    fn is_cmos_shift(&self) -> bool {
+        let mnemonic = self.variant.instruction_map()[&self.micro.opcode].mnemonic;
+        self.variant.is_cmos() && matches!(mnemonic, "ASL" | "LSR" | "ROL" | "ROR")
+    }
+
+    // Cycles spent on the effective address: one read, one write, or for
+    // read-modify-write instructions a read, a write of the unmodified value while the
+    // ALU works (a second read on the 65C02), and the final write
+    fn tick_operand(&mut self, step: u8, mode: &AddressingMode, access: Access) -> BusAccess {
+        let address = self.micro.address;
+
+        match (access, step) {
+            (Access::Read, _) => {
+                let bus_access = self.read_cycle(address);
+                self.execute_latched(mode, address, self.micro.data);
+                self.finish_read();
+                bus_access
+            }
+            (Access::Write, _) => {
+                let (address, data) = self.execute_latched(mode, address, 0);
+                self.finish();
+                self.write_cycle(address, data)
+            }
+            (Access::ReadModifyWrite, 0) => self.read_cycle(address),
+            (Access::ReadModifyWrite, 1) => {
+                let original = self.micro.data;
+                let bus_access = if self.variant.is_cmos() {
+                    self.read_cycle(address)
+                } else {
+                    self.write_cycle(address, original)
+                };
+                self.micro.data = self.execute_latched(mode, address, original).1;
+                bus_access
+            }
+            (Access::ReadModifyWrite, _) => {
+                self.finish();
+                self.write_cycle(address, self.micro.data)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cpu::error::IllegalOpcodePolicy;
+    use crate::cpu::variant::Variant;
+    use BusAccess::{Read, Write};
+
+    fn cpu_with_program(program: Vec<u8>) -> CPU {
+        cpu_with_variant(Variant::Nmos6502, program)
+    }
+
+    fn cpu_with_variant(variant: Variant, program: Vec<u8>) -> CPU {
+        let mut cpu = CPU::new(variant);
+        cpu.load(program);
+        cpu.reset();
+        cpu.cycles = 0;
+        cpu
+    }
+
+    // Ticks through one whole instruction, collecting its bus accesses
+    fn tick_instruction(cpu: &mut CPU) -> Vec<BusAccess> {
+        let mut accesses = vec![cpu.tick().unwrap()];
+        while !cpu.at_instruction_boundary() {
+            accesses.push(cpu.tick().unwrap());
+        }
+        accesses
+    }
+
+    #[test]
+    fn test_immediate() {
+        let mut cpu = cpu_with_program(vec![0xa9, 0x42]);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xa9
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x42
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x42);
+        assert_eq!(cpu.program_counter, 0x8002);
+        assert_eq!(cpu.cycles, 2);
+    }
+
+    #[test]
+    fn test_implied_dummy_read() {
+        let mut cpu = cpu_with_program(vec![0xe8, 0xea]);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xe8
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xea
+                },
+            ]
+        );
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.program_counter, 0x8001);
+    }
+
+    #[test]
+    fn test_absolute_x_read() {
+        let mut cpu = cpu_with_program(vec![0xbd, 0x00, 0x12]);
+        cpu.register_x = 0x10;
+        cpu.mem_write(0x1210, 0x55);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xbd
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1210,
+                    data: 0x55
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_absolute_x_read_page_crossed() {
+        let mut cpu = cpu_with_program(vec![0xbd, 0xF0, 0x12]);
+        cpu.register_x = 0x20;
+        cpu.mem_write(0x1310, 0x55);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xbd
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xF0
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1210,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x1310,
+                    data: 0x55
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_absolute_x_write() {
+        let mut cpu = cpu_with_program(vec![0x9d, 0x00, 0x12]);
+        cpu.register_a = 0x55;
+        cpu.register_x = 0x10;
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x9d
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1210,
+                    data: 0x00
+                },
+                Write {
+                    address: 0x1210,
+                    data: 0x55
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_page_read_modify_write() {
+        let mut cpu = cpu_with_program(vec![0x06, 0x10]);
+        cpu.mem_write(0x10, 0b0100_0001);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x06
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x10
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0b0100_0001
+                },
+                Write {
+                    address: 0x0010,
+                    data: 0b0100_0001
+                },
+                Write {
+                    address: 0x0010,
+                    data: 0b1000_0010
+                },
+            ]
+        );
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_absolute_x_read_modify_write() {
+        let mut cpu = cpu_with_program(vec![0xfe, 0xFF, 0x12]);
+        cpu.register_x = 0x01;
+        cpu.mem_write(0x1300, 0x41);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xfe
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xFF
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1200,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x1300,
+                    data: 0x41
+                },
+                Write {
+                    address: 0x1300,
+                    data: 0x41
+                },
+                Write {
+                    address: 0x1300,
+                    data: 0x42
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_page_x_dummy_read() {
+        let mut cpu = cpu_with_program(vec![0xb5, 0xF0]);
+        cpu.register_x = 0x20;
+        cpu.mem_write(0x10, 0x55);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xb5
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xF0
+                },
+                Read {
+                    address: 0x00F0,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x55
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_indirect_x() {
+        let mut cpu = cpu_with_program(vec![0xa1, 0x10]);
+        cpu.register_x = 0x04;
+        cpu.mem_write_u16(0x14, 0x1234);
+        cpu.mem_write(0x1234, 0x55);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xa1
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x10
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x0014,
+                    data: 0x34
+                },
+                Read {
+                    address: 0x0015,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1234,
+                    data: 0x55
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x55);
+    }
+
+    #[test]
+    fn test_indirect_y_write() {
+        let mut cpu = cpu_with_program(vec![0x91, 0x10]);
+        cpu.register_a = 0x55;
+        cpu.register_y = 0x04;
+        cpu.mem_write_u16(0x10, 0x1234);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x91
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x10
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x34
+                },
+                Read {
+                    address: 0x0011,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1238,
+                    data: 0x00
+                },
+                Write {
+                    address: 0x1238,
+                    data: 0x55
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_branch_taken_page_crossed() {
+        let mut cpu = cpu_with_program(vec![0xf0, 0xF0]);
+        cpu.set_status_bit(StatusFlag::Zero);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xf0
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xF0
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x80F2,
+                    data: 0x00
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x7FF2);
+    }
+
+    #[test]
+    fn test_branch_not_taken() {
+        let mut cpu = cpu_with_program(vec![0xf0, 0x10]);
+        assert_eq!(tick_instruction(&mut cpu).len(), 2);
+        assert_eq!(cpu.program_counter, 0x8002);
+    }
+
+    #[test]
+    fn test_jsr_rts() {
+        let mut cpu = cpu_with_program(vec![0x20, 0x04, 0x80, 0x00, 0x60]);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x20
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x04
+                },
+                Read {
+                    address: 0x01FD,
+                    data: 0x00
+                },
+                Write {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+                Write {
+                    address: 0x01FC,
+                    data: 0x02
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x80
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x8004);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8004,
+                    data: 0x60
+                },
+                Read {
+                    address: 0x8005,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x01FB,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x01FC,
+                    data: 0x02
+                },
+                Read {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x80
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x8003);
+        assert_eq!(cpu.stack_pointer, 0xFD);
+    }
+
+    #[test]
+    fn test_pha_pla() {
+        let mut cpu = cpu_with_program(vec![0x48, 0x68]);
+        cpu.register_a = 0x80;
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x48
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x68
+                },
+                Write {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+            ]
+        );
+        cpu.register_a = 0;
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8001,
+                    data: 0x68
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x01FC,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.is_flag_set(StatusFlag::Negative));
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_wrap_bug() {
+        let mut cpu = cpu_with_program(vec![0x6c, 0xFF, 0x02]);
+        cpu.mem_write(0x02FF, 0x34);
+        cpu.mem_write(0x0200, 0x12);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x6c
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xFF
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x02
+                },
+                Read {
+                    address: 0x02FF,
+                    data: 0x34
+                },
+                Read {
+                    address: 0x0200,
+                    data: 0x12
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x1234);
+    }
+
+    #[test]
+    fn test_brk() {
+        let mut cpu = cpu_with_program(vec![0x00, 0xFF]);
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x00
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xFF
+                },
+                Write {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+                Write {
+                    address: 0x01FC,
+                    data: 0x02
+                },
+                Write {
+                    address: 0x01FB,
+                    data: 0b0011_0100
+                },
+                Read {
+                    address: 0xFFFE,
+                    data: 0x00
+                },
+                Read {
+                    address: 0xFFFF,
+                    data: 0x90
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x9000);
+    }
+
+    #[test]
+    fn test_nmi_sequence() {
+        let mut cpu = cpu_with_program(vec![0xea]);
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.set_nmi(true);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xea
+                },
+                Read {
+                    address: 0x8000,
+                    data: 0xea
+                },
+                Write {
+                    address: 0x01FD,
+                    data: 0x80
+                },
+                Write {
+                    address: 0x01FC,
+                    data: 0x00
+                },
+                Write {
+                    address: 0x01FB,
+                    data: 0b0010_0100
+                },
+                Read {
+                    address: 0xFFFA,
+                    data: 0x00
+                },
+                Read {
+                    address: 0xFFFB,
+                    data: 0x90
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x9000);
+        assert_eq!(cpu.cycles, 7);
+    }
+
+    #[test]
+    fn test_undocumented_read_modify_write() {
+        let mut cpu = cpu_with_program(vec![0xc7, 0x10]);
+        cpu.register_a = 0x42;
+        cpu.mem_write(0x10, 0x43);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xc7
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x10
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x43
+                },
+                Write {
+                    address: 0x0010,
+                    data: 0x43
+                },
+                Write {
+                    address: 0x0010,
+                    data: 0x42
+                },
+            ]
+        );
+        assert!(cpu.is_flag_set(StatusFlag::Zero));
+    }
+
+    #[test]
+    fn test_unstable_store_page_crossed() {
+        let mut cpu = cpu_with_program(vec![0x9e, 0xF8, 0x12]);
+        cpu.register_x = 0x05;
+        cpu.register_y = 0x10;
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x9e
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xF8
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1208,
+                    data: 0x00
+                },
+                Write {
+                    address: 0x0108,
+                    data: 0x01
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_illegal_opcode() {
+        let mut cpu = cpu_with_program(vec![0x02, 0xe8]);
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Error;
+        assert_eq!(
+            cpu.tick(),
+            Err(CpuError::IllegalOpcode {
+                opcode: 0x02,
+                pc: 0x8000
+            })
+        );
+        assert_eq!(cpu.program_counter, 0x8000);
+        assert!(cpu.at_instruction_boundary());
+
+        cpu.illegal_opcode_policy = IllegalOpcodePolicy::Nop;
+        assert_eq!(tick_instruction(&mut cpu).len(), 2);
+        tick_instruction(&mut cpu);
+        assert_eq!(cpu.register_x, 1);
+    }
+
+    #[test]
+    fn test_matches_instruction_core() {
+        let program = vec![
+            0xa2, 0x00, 0xbd, 0x00, 0x02, 0x69, 0x01, 0x9d, 0x00, 0x03, 0xfe, 0x00, 0x03, 0xe8,
+            0xe0, 0x04, 0xd0, 0xf0, 0x20, 0x18, 0x80, 0x00, 0x00, 0x00, 0x48, 0x68, 0x60,
+        ];
+
+        let mut fast = CPU::new(Variant::Nmos6502);
+        let mut micro = CPU::new(Variant::Nmos6502);
+        for cpu in [&mut fast, &mut micro] {
+            for (i, byte) in [0x10, 0x20, 0x30, 0x40].iter().enumerate() {
+                cpu.mem_write(0x0200 + i as u16, *byte);
+            }
+            cpu.load(program.clone());
+            cpu.reset();
+        }
+
+        fast.run().unwrap();
+        // Stop in front of the BRK at $8015, where the instruction core halts
+        loop {
+            micro.tick().unwrap();
+            if micro.at_instruction_boundary() && micro.program_counter == 0x8015 {
+                break;
+            }
+        }
+
+        assert_eq!(micro.register_a, fast.register_a);
+        assert_eq!(micro.register_x, fast.register_x);
+        assert_eq!(micro.status, fast.status);
+        assert_eq!(micro.stack_pointer, fast.stack_pointer);
+        for addr in 0x0300..0x0304 {
+            assert_eq!(micro.mem_read(addr), fast.mem_read(addr));
+        }
+        assert_eq!(micro.cycles, fast.cycles);
+    }
+
+    // Variants
+
+    #[test]
+    fn test_cmos_zero_page_indirect() {
+        let mut cpu = cpu_with_variant(Variant::Cmos65C02, vec![0xb2, 0xff]);
+        cpu.mem_write(0x00FF, 0x34);
+        cpu.mem_write(0x0000, 0x12);
+        cpu.mem_write(0x1234, 0x42);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xb2
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xff
+                },
+                Read {
+                    address: 0x00FF,
+                    data: 0x34
+                },
+                // The pointer wraps within the zero page
+                Read {
+                    address: 0x0000,
+                    data: 0x12
+                },
+                Read {
+                    address: 0x1234,
+                    data: 0x42
+                },
+            ]
+        );
+        assert_eq!(cpu.register_a, 0x42);
+    }
+
+    #[test]
+    fn test_cmos_read_modify_write_reads_twice() {
+        let mut cpu = cpu_with_variant(Variant::Cmos65C02, vec![0xe6, 0x10]);
+        cpu.mem_write(0x0010, 0x41);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0xe6
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0x10
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x41
+                },
+                Read {
+                    address: 0x0010,
+                    data: 0x41
+                },
+                Write {
+                    address: 0x0010,
+                    data: 0x42
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cmos_jmp_indirect() {
+        let mut cpu = cpu_with_variant(Variant::Cmos65C02, vec![0x6c, 0xff, 0x30]);
+        cpu.mem_write(0x30FF, 0x80);
+        cpu.mem_write(0x3100, 0x90);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x6c
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xff
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x30
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x30
+                },
+                Read {
+                    address: 0x30FF,
+                    data: 0x80
+                },
+                Read {
+                    address: 0x3100,
+                    data: 0x90
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x9080);
+    }
+
+    #[test]
+    fn test_cmos_jmp_absolute_indexed_indirect() {
+        let mut cpu = cpu_with_variant(Variant::Cmos65C02, vec![0x7c, 0xff, 0x30]);
+        cpu.register_x = 0x01;
+        cpu.mem_write_u16(0x3100, 0x9080);
+        assert_eq!(
+            tick_instruction(&mut cpu),
+            vec![
+                Read {
+                    address: 0x8000,
+                    data: 0x7c
+                },
+                Read {
+                    address: 0x8001,
+                    data: 0xff
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x30
+                },
+                Read {
+                    address: 0x8002,
+                    data: 0x30
+                },
+                Read {
+                    address: 0x3100,
+                    data: 0x80
+                },
+                Read {
+                    address: 0x3101,
+                    data: 0x90
+                },
+            ]
+        );
+        assert_eq!(cpu.program_counter, 0x9080);
+    }
+
+    #[test]
+    fn test_wdc_stp_and_wai() {
+        let mut cpu = cpu_with_variant(Variant::Wdc65C02, vec![0xcb, 0xdb]);
+        assert_eq!(tick_instruction(&mut cpu).len(), 3);
+
+        // Waiting costs a cycle per tick without moving on
+        assert_eq!(
+            cpu.tick().unwrap(),
+            Read {
+                address: 0x8001,
+                data: 0xdb
+            }
+        );
+        assert_eq!(cpu.program_counter, 0x8001);
+
+        cpu.set_irq(true);
+        cpu.tick().unwrap();
+        cpu.tick().unwrap();
+        assert_eq!(cpu.tick(), Err(CpuError::Stopped { pc: 0x8001 }));
+        assert_eq!(cpu.tick(), Err(CpuError::Stopped { pc: 0x8001 }));
+    }
+
+    #[test]
+    fn test_jam_matches_instruction_core() {
+        let mut fast = cpu_with_program(vec![0xe8, 0x02]);
+        let mut micro = cpu_with_program(vec![0xe8, 0x02]);
+
+        fast.step().unwrap();
+        assert_eq!(fast.step(), Err(CpuError::Jammed { pc: 0x8001 }));
+
+        // The tick that executes JAM reports it, not the one after
+        tick_instruction(&mut micro);
+        micro.tick().unwrap();
+        assert_eq!(micro.tick(), Err(CpuError::Jammed { pc: 0x8001 }));
+        assert_eq!(micro.program_counter, fast.program_counter);
+        assert_eq!(micro.register_x, fast.register_x);
+    }
+
+    #[test]
+    fn test_switching_cores_mid_instruction() {
+        // LDA #$42; TAX; BRK
+        let mut cpu = cpu_with_program(vec![0xa9, 0x42, 0xaa, 0x00]);
+        cpu.tick().unwrap();
+
+        // The fast core would take the operand for an opcode
+        assert_eq!(
+            cpu.step(),
+            Err(CpuError::InstructionInProgress { pc: 0x8000 })
+        );
+        assert_eq!(
+            cpu.run(),
+            Err(CpuError::InstructionInProgress { pc: 0x8000 })
+        );
+
+        // Once the instruction is finished, either core can carry on
+        cpu.tick().unwrap();
+        assert_eq!(cpu.step().unwrap().mnemonic, "TAX");
+        assert_eq!(cpu.register_x, 0x42);
+        cpu.tick().unwrap();
+        assert!(!cpu.at_instruction_boundary());
+    }
+
+    #[test]
+    fn test_cmos_matches_instruction_core() {
+        let program = vec![
+            0xf8, 0xa9, 0x19, 0x72, 0x20, 0xd8, 0xa2, 0x01, 0x1e, 0x00, 0x02, 0xda, 0x7a, 0x9c,
+            0x01, 0x02, 0x0c, 0x02, 0x02, 0x1f, 0x22, 0x01, 0xea, 0x80, 0x03, 0xea, 0xea, 0xea,
+            0x6c, 0x10, 0x02, 0xea, 0x1a, 0x00,
+        ];
+
+        let mut fast = CPU::new(Variant::Wdc65C02);
+        let mut micro = CPU::new(Variant::Wdc65C02);
+        for cpu in [&mut fast, &mut micro] {
+            for (i, byte) in [0x10, 0x20, 0x30, 0x40].iter().enumerate() {
+                cpu.mem_write(0x0200 + i as u16, *byte);
+            }
+            cpu.mem_write_u16(0x0020, 0x0200);
+            cpu.mem_write_u16(0x0210, 0x8020);
+            cpu.load(program.clone());
+            cpu.reset();
+        }
+
+        // Compare after every instruction, so each one has the same timing
+        while fast.program_counter != 0x8021 {
+            let step = fast.step().unwrap();
+            tick_instruction(&mut micro);
+
+            assert_eq!(micro.program_counter, fast.program_counter, "{:?}", step);
+            assert_eq!(micro.cycles, fast.cycles, "{:?}", step);
+            assert_eq!(micro.register_a, fast.register_a);
+            assert_eq!(micro.register_y, fast.register_y);
+            assert_eq!(micro.status, fast.status);
+        }
+        for addr in 0x0200..0x0204 {
+            assert_eq!(micro.mem_read(addr), fast.mem_read(addr));
+        }
+    }
+
+    // Runs every opcode of the 65C02 variants once on each core, with and without
+    // page crossings and decimal mode, and compares the timing and the state left
+    #[test]
+    fn test_cmos_every_opcode_matches_instruction_core() {
+        for variant in [Variant::Cmos65C02, Variant::Wdc65C02] {
+            let mut opcodes: Vec<u8> = variant.instruction_map().keys().copied().collect();
+            opcodes.sort();
+
+            for opcode in opcodes {
+                for (index, status) in [(0x01, 0x24), (0xF0, 0x2D)] {
+                    let mut fast = CPU::new(variant);
+                    let mut micro = CPU::new(variant);
+                    for cpu in [&mut fast, &mut micro] {
+                        for addr in 0x0000..0x0400 {
+                            cpu.mem_write(addr, (addr as u8).wrapping_mul(7).wrapping_add(3));
+                        }
+                        cpu.mem_write_u16(0xFFFE, 0x9000);
+                        cpu.load(vec![opcode, 0x10, 0x02]);
+                        cpu.reset();
+                        cpu.halt_on_brk = false;
+                        cpu.register_a = 0x35;
+                        cpu.register_x = index;
+                        cpu.register_y = index;
+                        cpu.status = status;
+                        cpu.cycles = 0;
+                    }
+
+                    let context = format!("{:?} opcode ${:02X} X=${:02X}", variant, opcode, index);
+                    let expected = fast.step().map(|_| ());
+                    let mut result = Ok(());
+                    let mut ticks = 0;
+                    loop {
+                        ticks += 1;
+                        if let Err(error) = micro.tick() {
+                            result = Err(error);
+                            break;
+                        }
+                        if micro.at_instruction_boundary() {
+                            break;
+                        }
+                    }
+
+                    assert_eq!(result, expected, "{}", context);
+                    if expected.is_err() {
+                        continue;
+                    }
+                    assert_eq!(ticks, fast.cycles, "{}", context);
+                    assert_eq!(micro.program_counter, fast.program_counter, "{}", context);
+                    assert_eq!(micro.register_a, fast.register_a, "{}", context);
+                    assert_eq!(micro.register_x, fast.register_x, "{}", context);
+                    assert_eq!(micro.register_y, fast.register_y, "{}", context);
+                    assert_eq!(micro.status, fast.status, "{}", context);
+                    assert_eq!(micro.stack_pointer, fast.stack_pointer, "{}", context);
+                    for addr in 0x0000..0x0400 {
+                        assert_eq!(micro.mem_read(addr), fast.mem_read(addr), "{}", context);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
 pub enum StatusFlag {
     Carry = 0b0000_0001,
     Zero = 0b0000_0010,
+    InterruptDisable = 0b0000_0100,
+    Decimal = 0b0000_1000,
+    Break = 0b0001_0000,
+    Unused = 0b0010_0000, /* Always reads as one */
     Overflow = 0b0100_0000,
     Negative = 0b1000_0000,
 }
@@ -0,0 +1,15 @@
+use super::instruction_set::instruction::addressing_mode::AddressingMode;
+
+// What a single call to `CPU::step` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub address: u16, /* of the opcode */
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+    pub effective_address: Option<u16>, /* None for implied, immediate and relative modes */
+    pub cycles: u64, /* including any interrupt serviced before the instruction */
+    pub branch_taken: bool,
+    // BRK was fetched while `halt_on_brk` is set, so nothing was executed
+    pub halted: bool,
+}
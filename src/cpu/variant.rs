@@ -0,0 +1,35 @@
+use super::instruction_set::instruction::Instruction;
+use super::instruction_set::{CMOS_INSTRUCTION_MAP, NMOS_INSTRUCTION_MAP, WDC_INSTRUCTION_MAP};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    // The original MOS 6502, including its undocumented opcodes
+    #[default]
+    Nmos6502,
+    // The NES CPU: an NMOS 6502 whose decimal mode is disconnected
+    Ricoh2A03,
+    // Adds new instructions and (zp) addressing, fixes the JMP indirect bug and clears
+    // Decimal on interrupts. Unused opcodes are NOPs.
+    Cmos65C02,
+    // A 65C02 with WAI, STP and the Rockwell bit instructions
+    Wdc65C02,
+}
+
+impl Variant {
+    pub fn instruction_map(self) -> &'static HashMap<u8, &'static Instruction> {
+        match self {
+            Variant::Nmos6502 | Variant::Ricoh2A03 => &NMOS_INSTRUCTION_MAP,
+            Variant::Cmos65C02 => &CMOS_INSTRUCTION_MAP,
+            Variant::Wdc65C02 => &WDC_INSTRUCTION_MAP,
+        }
+    }
+
+    pub fn is_cmos(self) -> bool {
+        matches!(self, Variant::Cmos65C02 | Variant::Wdc65C02)
+    }
+
+    pub fn has_decimal_mode(self) -> bool {
+        self != Variant::Ricoh2A03
+    }
+}
@@ -0,0 +1,252 @@
+// Turns machine code back into assembly, one instruction per line:
+//
+//     8004  3D 20 43  AND $4320,X
+//
+// Bytes that do not start a known instruction, or whose instruction runs past the end
+// of the input, are listed one at a time as `.byte`.
+
+pub mod flow;
+
+use crate::cpu::bus::Bus;
+use crate::cpu::instruction_set::instruction::addressing_mode::AddressingMode;
+use crate::cpu::instruction_set::instruction::Instruction;
+use crate::cpu::variant::Variant;
+use crate::util;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    // None for a `.byte` line
+    pub instruction: Option<&'static Instruction>,
+}
+
+impl Line {
+    // The assembly text without the address and bytes, e.g. `AND $4320,X`
+    pub fn text(&self) -> String {
+        let instruction = match self.instruction {
+            Some(instruction) => instruction,
+            None => return format!(".byte ${:02X}", self.bytes[0]),
+        };
+
+        let operand = instruction
+            .mode
+            .format_operand(&self.bytes[1..], self.address);
+        if operand.is_empty() {
+            instruction.mnemonic.to_string()
+        } else {
+            format!("{} {}", instruction.mnemonic, operand)
+        }
+    }
+
+    // Where a branch, JMP or JSR transfers control to, when that is known without
+    // running the code. Indirect jumps depend on memory, so they have no target.
+    pub fn target(&self) -> Option<u16> {
+        let instruction = self.instruction?;
+        let next = self.address.wrapping_add(self.bytes.len() as u16);
+
+        match instruction.mode {
+            AddressingMode::Relative => Some(util::get_address_from_offset(next, self.bytes[1])),
+            AddressingMode::ZeroPageRelative => {
+                Some(util::get_address_from_offset(next, self.bytes[2]))
+            }
+            AddressingMode::Absolute if matches!(instruction.mnemonic, "JMP" | "JSR") => {
+                Some(u16::from_le_bytes([self.bytes[1], self.bytes[2]]))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(
+            f,
+            "{:04X}  {:<8}  {}",
+            self.address,
+            bytes.join(" "),
+            self.text()
+        )
+    }
+}
+
+// Iterator over the lines of a block of code. Undocumented opcodes are listed as
+// `.byte` unless `with_undocumented` is set, since in a listing they are more likely to
+// be data than code.
+pub struct Disassembly<'a> {
+    bytes: Cow<'a, [u8]>,
+    origin: u16,
+    offset: usize,
+    variant: Variant,
+    undocumented: bool,
+}
+
+impl<'a> Disassembly<'a> {
+    pub fn with_undocumented(mut self, undocumented: bool) -> Self {
+        self.undocumented = undocumented;
+        self
+    }
+
+    fn decode(&self, opcode: u8) -> Option<&'static Instruction> {
+        let instruction = self.variant.instruction_map().get(&opcode).copied()?;
+        if instruction.documented || self.undocumented {
+            Some(instruction)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Iterator for Disassembly<'a> {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        let remaining = &self.bytes[self.offset..];
+        let opcode = *remaining.first()?;
+        let address = self.origin.wrapping_add(self.offset as u16);
+
+        let instruction = self
+            .decode(opcode)
+            .filter(|instruction| instruction.length as usize <= remaining.len());
+        let length = instruction.map_or(1, |instruction| instruction.length as usize);
+
+        self.offset += length;
+        Some(Line {
+            address,
+            bytes: remaining[..length].to_vec(),
+            instruction,
+        })
+    }
+}
+
+// Disassembles `bytes` as if loaded at `origin`
+pub fn disassemble(bytes: &[u8], origin: u16, variant: Variant) -> Disassembly<'_> {
+    Disassembly {
+        bytes: Cow::Borrowed(bytes),
+        origin,
+        offset: 0,
+        variant,
+        undocumented: false,
+    }
+}
+
+// Disassembles a range of memory without disturbing any device on the bus. The listing
+// ends early at the first address the bus cannot peek.
+pub fn disassemble_bus<B: Bus>(
+    bus: &B,
+    range: RangeInclusive<u16>,
+    variant: Variant,
+) -> Disassembly<'static> {
+    let origin = *range.start();
+    let bytes = range.map_while(|address| bus.peek(address)).collect();
+
+    Disassembly {
+        bytes: Cow::Owned(bytes),
+        origin,
+        offset: 0,
+        variant,
+        undocumented: false,
+    }
+}
+
+// Renders lines as a listing, one per line
+pub fn listing<I>(lines: I) -> String
+where
+    I: IntoIterator<Item = Line>,
+{
+    lines
+        .into_iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cpu::bus::Ram;
+
+    #[test]
+    fn test_line_format() {
+        let bytes = [0x3d, 0x20, 0x43];
+        let line = disassemble(&bytes, 0x8004, Variant::Nmos6502)
+            .next()
+            .unwrap();
+        assert_eq!(line.to_string(), "8004  3D 20 43  AND $4320,X");
+        assert_eq!(line.text(), "AND $4320,X");
+    }
+
+    #[test]
+    fn test_listing() {
+        // LDA #$05; loop: ASL A; DEX; BNE loop; JSR $9000; BRK
+        let bytes = [0xa9, 0x05, 0x0a, 0xca, 0xd0, 0xfc, 0x20, 0x00, 0x90, 0x00];
+        assert_eq!(
+            listing(disassemble(&bytes, 0x8000, Variant::Nmos6502)),
+            "8000  A9 05     LDA #$05\n\
+             8002  0A        ASL A\n\
+             8003  CA        DEX\n\
+             8004  D0 FC     BNE $8002\n\
+             8006  20 00 90  JSR $9000\n\
+             8009  00        BRK\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_bytes() {
+        // SLO is undocumented, and the LDA at the end is missing its operand
+        let bytes = [0x07, 0xad, 0x00];
+        let lines: Vec<String> = disassemble(&bytes, 0x8000, Variant::Nmos6502)
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "8000  07        .byte $07",
+                "8001  AD        .byte $AD",
+                "8002  00        BRK",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_undocumented() {
+        let bytes = [0x07, 0x10];
+        let line = disassemble(&bytes, 0x8000, Variant::Nmos6502)
+            .with_undocumented(true)
+            .next()
+            .unwrap();
+        assert_eq!(line.text(), "SLO $10");
+    }
+
+    #[test]
+    fn test_variants() {
+        let bytes = [0xb2, 0x20, 0x0f, 0x10, 0xfd];
+        let cmos: Vec<String> = disassemble(&bytes, 0x8000, Variant::Wdc65C02)
+            .map(|line| line.text())
+            .collect();
+        assert_eq!(cmos, vec!["LDA ($20)", "BBR0 $10,$8002"]);
+    }
+
+    #[test]
+    fn test_targets() {
+        let bytes = [0xd0, 0xfe, 0x4c, 0x34, 0x12, 0x6c, 0x34, 0x12, 0xa9, 0x00];
+        let targets: Vec<Option<u16>> = disassemble(&bytes, 0x8000, Variant::Nmos6502)
+            .map(|line| line.target())
+            .collect();
+        assert_eq!(targets, vec![Some(0x8000), Some(0x1234), None, None]);
+    }
+
+    #[test]
+    fn test_disassemble_bus() {
+        let mut ram = Ram::new();
+        ram.write(0xFFFE, 0xe8);
+        ram.write(0xFFFF, 0xea);
+        let lines: Vec<String> = disassemble_bus(&ram, 0xFFFE..=0xFFFF, Variant::Nmos6502)
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(lines, vec!["FFFE  E8        INX", "FFFF  EA        NOP"]);
+    }
+}
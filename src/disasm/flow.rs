@@ -0,0 +1,440 @@
+// Flow-following disassembly. Starting from the interrupt vectors and any extra entry
+// points, it decodes instructions along every path control can take: falling through,
+// branches, JMP and JSR. Whatever is never reached is treated as data, so tables
+// embedded in the code are not misread as instructions.
+//
+// The listing labels every branch, JMP and JSR target inside the image, and can be fed
+// back to the assembler. Where the assembler would pick another opcode for the same
+// mnemonic and mode, as for most undocumented NOPs, the instruction is listed as
+// `.byte` with its text in a comment.
+
+use super::{disassemble, Line};
+use crate::asm::Assembler;
+use crate::cpu::instruction_set::instruction::addressing_mode::AddressingMode;
+use crate::cpu::variant::Variant;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const NMI_VECTOR: u16 = 0xFFFA;
+const RESET_VECTOR: u16 = 0xFFFC;
+const IRQ_VECTOR: u16 = 0xFFFE;
+
+// Data bytes per `.byte` line
+const BYTES_PER_LINE: usize = 8;
+
+pub struct FlowDisassembler<'a> {
+    bytes: &'a [u8],
+    origin: u16,
+    variant: Variant,
+    entry_points: Vec<u16>,
+    undocumented: bool,
+}
+
+impl<'a> FlowDisassembler<'a> {
+    // `bytes` is an image loaded at `origin`. When it covers $FFFA-$FFFF, the vectors
+    // found there are followed too.
+    pub fn new(bytes: &'a [u8], origin: u16, variant: Variant) -> Self {
+        FlowDisassembler {
+            bytes,
+            origin,
+            variant,
+            entry_points: vec![],
+            undocumented: false,
+        }
+    }
+
+    pub fn entry_point(mut self, address: u16) -> Self {
+        self.entry_points.push(address);
+        self
+    }
+
+    // Follows undocumented opcodes instead of stopping at them
+    pub fn with_undocumented(mut self, undocumented: bool) -> Self {
+        self.undocumented = undocumented;
+        self
+    }
+
+    pub fn run(self) -> Analysis {
+        let mut analysis = Analysis {
+            bytes: self.bytes.to_vec(),
+            origin: self.origin,
+            code: vec![false; self.bytes.len()],
+            lines: BTreeMap::new(),
+            labels: BTreeMap::new(),
+            assembler: Assembler::new(self.variant),
+        };
+
+        let mut pending = vec![];
+        for (vector, name) in [
+            (RESET_VECTOR, "reset"),
+            (NMI_VECTOR, "nmi"),
+            (IRQ_VECTOR, "irq"),
+        ] {
+            if let Some(target) = analysis.word_at(vector) {
+                analysis.add_label(target, name.to_string());
+                pending.push(target);
+            }
+        }
+        for &address in &self.entry_points {
+            analysis.add_label(address, format!("sub_{:04X}", address));
+            pending.push(address);
+        }
+
+        while let Some(address) = pending.pop() {
+            let line = match self.decode(&analysis, address) {
+                Some(line) => line,
+                None => continue,
+            };
+
+            let instruction = line.instruction.unwrap();
+            let next = address.wrapping_add(line.bytes.len() as u16);
+            let target = line.target();
+
+            match (instruction.mnemonic, target) {
+                ("JSR", Some(target)) => {
+                    analysis.add_label(target, format!("sub_{:04X}", target));
+                    pending.push(target);
+                }
+                (_, Some(target)) => {
+                    analysis.add_label(target, format!("L{:04X}", target));
+                    pending.push(target);
+                }
+                _ => {}
+            }
+            if falls_through(instruction.mnemonic) {
+                pending.push(next);
+            }
+
+            analysis.mark_code(&line);
+        }
+
+        analysis
+    }
+
+    // Decodes the instruction at `address`, unless it is outside the image, already
+    // decoded, not a known instruction, or overlaps code decoded along another path
+    fn decode(&self, analysis: &Analysis, address: u16) -> Option<Line> {
+        let offset = analysis.offset(address)?;
+        if analysis.code[offset] {
+            return None;
+        }
+
+        let line = disassemble(&self.bytes[offset..], address, self.variant)
+            .with_undocumented(self.undocumented)
+            .next()?;
+        line.instruction?;
+
+        let end = offset + line.bytes.len();
+        if analysis.code[offset..end].iter().any(|&code| code) {
+            return None;
+        }
+        Some(line)
+    }
+}
+
+// Whether execution can continue with the next instruction. BRK is assumed not to
+// return, since it mostly marks the end of code or a crash.
+fn falls_through(mnemonic: &str) -> bool {
+    !matches!(
+        mnemonic,
+        "JMP" | "RTS" | "RTI" | "BRK" | "BRA" | "STP" | "JAM"
+    )
+}
+
+pub struct Analysis {
+    bytes: Vec<u8>,
+    origin: u16,
+    // Per byte of the image: whether some reached instruction covers it
+    code: Vec<bool>,
+    lines: BTreeMap<u16, Line>,
+    labels: BTreeMap<u16, String>,
+    assembler: Assembler,
+}
+
+impl Analysis {
+    pub fn is_code(&self, address: u16) -> bool {
+        self.offset(address).is_some_and(|offset| self.code[offset])
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(|label| label.as_str())
+    }
+
+    // The decoded instructions in address order
+    pub fn instructions(&self) -> impl Iterator<Item = &Line> {
+        self.lines.values()
+    }
+
+    // A source listing that assembles back to the same image
+    pub fn listing(&self) -> String {
+        let mut listing = String::new();
+        writeln!(listing, "        .org ${:04X}", self.origin).unwrap();
+
+        let mut offset = 0;
+        while offset < self.bytes.len() {
+            let address = self.address(offset);
+            if let Some(label) = self.labels.get(&address) {
+                writeln!(listing, "{}:", label).unwrap();
+            }
+
+            if let Some(line) = self.lines.get(&address) {
+                let text = self.line_text(line);
+                if self.reassembles(line) {
+                    writeln!(listing, "        {}", text).unwrap();
+                } else {
+                    let bytes: Vec<String> = line
+                        .bytes
+                        .iter()
+                        .map(|byte| format!("${:02X}", byte))
+                        .collect();
+                    writeln!(listing, "        .byte {} ; {}", bytes.join(", "), text).unwrap();
+                }
+                offset += line.bytes.len();
+            } else if self.is_vector_table(address) {
+                let words: Vec<String> =
+                    (0..3).map(|i| self.word_operand(address + i * 2)).collect();
+                writeln!(listing, "        .word {}", words.join(", ")).unwrap();
+                offset += 6;
+            } else {
+                let length = self.data_run(offset);
+                let bytes: Vec<String> = self.bytes[offset..offset + length]
+                    .iter()
+                    .map(|byte| format!("${:02X}", byte))
+                    .collect();
+                writeln!(listing, "        .byte {}", bytes.join(", ")).unwrap();
+                offset += length;
+            }
+        }
+
+        listing
+    }
+
+    fn offset(&self, address: u16) -> Option<usize> {
+        let offset = address.checked_sub(self.origin)? as usize;
+        (offset < self.bytes.len()).then_some(offset)
+    }
+
+    fn address(&self, offset: usize) -> u16 {
+        self.origin.wrapping_add(offset as u16)
+    }
+
+    fn word_at(&self, address: u16) -> Option<u16> {
+        let lo = self.bytes[self.offset(address)?];
+        let hi = self.bytes[self.offset(address.wrapping_add(1))?];
+        Some(u16::from_le_bytes([lo, hi]))
+    }
+
+    // Vector names take precedence over generated labels, and subroutine labels over
+    // branch labels. Targets outside the image get no label.
+    fn add_label(&mut self, address: u16, label: String) {
+        if self.offset(address).is_none() {
+            return;
+        }
+        let rank = |label: &str| match label {
+            _ if label.starts_with("sub_") => 1,
+            _ if label.starts_with('L') => 0,
+            _ => 2,
+        };
+        match self.labels.get(&address) {
+            Some(existing) if rank(existing) >= rank(&label) => {}
+            _ => {
+                self.labels.insert(address, label);
+            }
+        }
+    }
+
+    fn mark_code(&mut self, line: &Line) {
+        let offset = self.offset(line.address).unwrap();
+        for code in &mut self.code[offset..offset + line.bytes.len()] {
+            *code = true;
+        }
+        self.lines.insert(line.address, line.clone());
+    }
+
+    // Jump targets that land on a label are written as that label. A target inside
+    // another instruction has no line to attach a label to, so it stays numeric.
+    fn line_text(&self, line: &Line) -> String {
+        let target = line
+            .target()
+            .filter(|target| self.lines.contains_key(target))
+            .and_then(|target| self.labels.get(&target));
+        let (instruction, label) = match (line.instruction, target) {
+            (Some(instruction), Some(label)) => (instruction, label),
+            _ => return line.text(),
+        };
+
+        match instruction.mode {
+            AddressingMode::ZeroPageRelative => {
+                format!("{} ${:02X},{}", instruction.mnemonic, line.bytes[1], label)
+            }
+            _ => format!("{} {}", instruction.mnemonic, label),
+        }
+    }
+
+    // Whether assembling the instruction's text gives back its opcode
+    fn reassembles(&self, line: &Line) -> bool {
+        let instruction = line.instruction.unwrap();
+        self.assembler
+            .opcode(instruction.mnemonic, instruction.mode)
+            == Some(instruction.opcode)
+    }
+
+    fn is_vector_table(&self, address: u16) -> bool {
+        address == NMI_VECTOR && self.offset(IRQ_VECTOR.wrapping_add(1)).is_some()
+    }
+
+    fn word_operand(&self, address: u16) -> String {
+        let word = self.word_at(address).unwrap();
+        match self.labels.get(&word) {
+            Some(label) if self.lines.contains_key(&word) => label.clone(),
+            _ => format!("${:04X}", word),
+        }
+    }
+
+    // Length of the `.byte` line starting at `offset`. It ends at the next code, label
+    // or vector table.
+    fn data_run(&self, offset: usize) -> usize {
+        let mut length = 1;
+        while length < BYTES_PER_LINE && offset + length < self.bytes.len() {
+            let address = self.address(offset + length);
+            if self.code[offset + length]
+                || self.labels.contains_key(&address)
+                || self.is_vector_table(address)
+            {
+                break;
+            }
+            length += 1;
+        }
+        length
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // An image at $8000 with vectors at the top, so it ends at $FFFF
+    fn rom(code: &[u8], reset: u16, nmi: u16, irq: u16) -> Vec<u8> {
+        let mut rom = vec![0xff; 0x8000];
+        rom[..code.len()].copy_from_slice(code);
+        rom[0x7FFA..].copy_from_slice(&[
+            nmi as u8,
+            (nmi >> 8) as u8,
+            reset as u8,
+            (reset >> 8) as u8,
+            irq as u8,
+            (irq >> 8) as u8,
+        ]);
+        rom
+    }
+
+    #[test]
+    fn test_separates_code_from_data() {
+        let code = [
+            // reset: LDX #$00
+            0xa2, 0x00, //
+            // L8002: LDA $800D,X; JSR sub_8012; INX; BNE L8002; JMP L8002
+            0xbd, 0x0d, 0x80, 0x20, 0x12, 0x80, 0xe8, 0xd0, 0xf7, 0x4c, 0x02, 0x80,
+            // Table at $800E, which would decode as ASL/LDA if it was code
+            0x0a, 0xad, 0x00, 0x00, //
+            // sub_8012: RTS
+            0x60,
+        ];
+        let image = rom(&code, 0x8000, 0x8012, 0x8012);
+        let analysis = FlowDisassembler::new(&image, 0x8000, Variant::Nmos6502).run();
+
+        assert!(analysis.is_code(0x8000));
+        assert!(analysis.is_code(0x800D));
+        assert!(!analysis.is_code(0x800E));
+        assert!(!analysis.is_code(0x8011));
+        assert!(analysis.is_code(0x8012));
+        assert_eq!(analysis.label(0x8000), Some("reset"));
+        assert_eq!(analysis.label(0x8002), Some("L8002"));
+        assert_eq!(analysis.label(0x8012), Some("nmi"));
+        assert_eq!(analysis.instructions().count(), 7);
+    }
+
+    #[test]
+    fn test_listing() {
+        let image = [
+            // reset: JSR irq; BEQ LFFF6; JMP ($0300)
+            0x20, 0xf7, 0xff, 0xf0, 0x04, 0x6c, 0x00, 0x03, //
+            // Never reached
+            0xea, //
+            // LFFF6: CLI; irq: LDA #$01; RTS
+            0x58, 0xa9, 0x01, 0x60, //
+            // Vectors, with NMI outside the image
+            0x00, 0x80, 0xed, 0xff, 0xf7, 0xff,
+        ];
+        let analysis = FlowDisassembler::new(&image, 0xFFED, Variant::Nmos6502).run();
+
+        assert_eq!(
+            analysis.listing(),
+            "        .org $FFED\n\
+             reset:\n\
+             \x20       JSR irq\n\
+             \x20       BEQ LFFF6\n\
+             \x20       JMP ($0300)\n\
+             \x20       .byte $EA\n\
+             LFFF6:\n\
+             \x20       CLI\n\
+             irq:\n\
+             \x20       LDA #$01\n\
+             \x20       RTS\n\
+             \x20       .word $8000, reset, irq\n"
+        );
+    }
+
+    #[test]
+    fn test_entry_points() {
+        // JSR $9010 at an address no vector points to
+        let analysis = FlowDisassembler::new(&[0x20, 0x10, 0x90], 0x9000, Variant::Nmos6502)
+            .entry_point(0x9000)
+            .run();
+        assert!(analysis.is_code(0x9002));
+        assert_eq!(analysis.label(0x9000), Some("sub_9000"));
+        // Outside the image, so not labelled
+        assert_eq!(analysis.label(0x9010), None);
+    }
+
+    #[test]
+    fn test_stops_at_unknown_opcodes() {
+        // reset: NOP; then an undocumented SLO
+        let image = rom(&[0xea, 0x07, 0x10], 0x8000, 0x8000, 0x8000);
+        let analysis = FlowDisassembler::new(&image, 0x8000, Variant::Nmos6502).run();
+        assert!(analysis.is_code(0x8000));
+        assert!(!analysis.is_code(0x8001));
+
+        let analysis = FlowDisassembler::new(&image, 0x8000, Variant::Nmos6502)
+            .with_undocumented(true)
+            .run();
+        assert!(analysis.is_code(0x8001));
+    }
+
+    #[test]
+    fn test_listing_reassembles_undocumented_opcodes() {
+        let code = [
+            // NOP, the documented one and two undocumented ones
+            0xea, 0x1a, 0xfa, //
+            // NOP #imm, zp, zp,X, abs and abs,X
+            0x80, 0x01, 0x82, 0x01, 0x04, 0x10, 0x44, 0x10, 0x14, 0x10, 0x34, 0x10, 0x0c, 0x00,
+            0x02, 0x1c, 0x00, 0x02, 0x3c, 0x00, 0x02, //
+            // SBC #imm and its undocumented copy, which has a name of its own; LAX zp
+            0xe9, 0x01, 0xeb, 0x01, 0xa7, 0x10, //
+            0x00,
+        ];
+        let image = rom(&code, 0x8000, 0x8000, 0x8000);
+        let analysis = FlowDisassembler::new(&image, 0x8000, Variant::Nmos6502)
+            .with_undocumented(true)
+            .run();
+        assert_eq!(analysis.instructions().count(), 16);
+
+        let listing = analysis.listing();
+        assert!(listing.contains("        NOP\n        .byte $1A ; NOP\n"));
+        assert!(listing.contains("        USBC #$01\n"));
+        let program = Assembler::new(Variant::Nmos6502)
+            .assemble(&listing)
+            .unwrap();
+        assert_eq!(program.bytes, image);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod asm;
+pub mod cpu;
+pub mod disasm;
+mod util;
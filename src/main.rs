@@ -1,6 +1,3 @@
-mod cpu;
-mod util;
-
 fn main() {
     println!("Work in progress!");
 }
@@ -6,12 +6,11 @@ pub fn get_bit_at(input: u8, pos: u8) -> bool {
     }
 }
 
+// Relative offsets are two's complement, so 0xFE steps back by two bytes
 pub fn get_address_from_offset(addr: u16, offset: u8) -> u16 {
-    let value = offset & 0b0111_1111;
+    addr.wrapping_add(offset as i8 as u16)
+}
 
-    if get_bit_at(offset, 7) {
-        addr.wrapping_sub(value as u16)
-    } else {
-        addr.wrapping_add(value as u16)
-    }
+pub fn is_page_crossed(a: u16, b: u16) -> bool {
+    a & 0xFF00 != b & 0xFF00
 }